@@ -1,3 +1,6 @@
+mod keys;
+mod timestamps;
+
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -6,6 +9,9 @@ use serde::Deserialize;
 
 use crate::macros::ok_or_return;
 
+pub use self::keys::{KeyBinding, Keys};
+pub use self::timestamps::Timestamps;
+
 #[derive(Debug, Clone, Copy, Default, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum RoomsSortOrder {
@@ -14,6 +20,15 @@ pub enum RoomsSortOrder {
     Importance,
 }
 
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Notify {
+    Off,
+    #[default]
+    Mentions,
+    All,
+}
+
 #[derive(Debug, Clone, Default, Deserialize)]
 pub struct EuphRoom {
     // TODO Mark favourite rooms via printable ascii characters
@@ -23,6 +38,8 @@ pub struct EuphRoom {
     #[serde(default)]
     pub force_username: bool,
     pub password: Option<String>,
+    #[serde(default)]
+    pub notify: Notify,
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -39,8 +56,12 @@ pub struct Config {
     pub offline: bool,
     #[serde(default)]
     pub rooms_sort_order: RoomsSortOrder,
-    // TODO Invoke external notification command?
+    #[serde(flatten)]
+    pub timestamps: Timestamps,
+    pub notify_command: Option<Vec<String>>,
     pub euph: Euph,
+    #[serde(default)]
+    pub keys: Keys,
 }
 
 impl Config {
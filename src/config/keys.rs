@@ -0,0 +1,472 @@
+//! Named actions and the key presses that trigger them.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crossterm::event::KeyCode;
+use serde::Deserialize;
+
+use crate::ui::InputEvent;
+
+/// A single key press, optionally combined with modifier keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPress {
+    pub code: KeyCode,
+    /// Ignored for characters since the shift key is already reflected in the
+    /// character itself.
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+}
+
+impl KeyPress {
+    fn matches(&self, event: &InputEvent) -> bool {
+        match event {
+            InputEvent::Key(key) => {
+                key.code == self.code
+                    && key.ctrl == self.ctrl
+                    && key.alt == self.alt
+                    && (matches!(self.code, KeyCode::Char(_)) || key.shift == self.shift)
+            }
+            InputEvent::Paste(_) => false,
+        }
+    }
+
+    fn modifiers(&self) -> String {
+        let mut result = String::new();
+        if self.ctrl {
+            result.push_str("ctrl+");
+        }
+        if self.alt {
+            result.push_str("alt+");
+        }
+        if self.shift && !matches!(self.code, KeyCode::Char(_)) {
+            result.push_str("shift+");
+        }
+        result
+    }
+
+    fn key(&self) -> String {
+        match self.code {
+            KeyCode::Char(' ') => "space".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::F(n) => format!("F{n}"),
+            KeyCode::Up => "↑".to_string(),
+            KeyCode::Down => "↓".to_string(),
+            KeyCode::Left => "←".to_string(),
+            KeyCode::Right => "→".to_string(),
+            KeyCode::Enter => "enter".to_string(),
+            KeyCode::Esc => "esc".to_string(),
+            KeyCode::Tab => "tab".to_string(),
+            KeyCode::BackTab => "backtab".to_string(),
+            KeyCode::Backspace => "backspace".to_string(),
+            KeyCode::Delete => "delete".to_string(),
+            KeyCode::Insert => "insert".to_string(),
+            KeyCode::Home => "home".to_string(),
+            KeyCode::End => "end".to_string(),
+            KeyCode::PageUp => "pageup".to_string(),
+            KeyCode::PageDown => "pagedown".to_string(),
+            code => format!("{code:?}").to_lowercase(),
+        }
+    }
+}
+
+impl fmt::Display for KeyPress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.modifiers(), self.key())
+    }
+}
+
+impl FromStr for KeyPress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // A trailing "+" is the plus key, not a separator.
+        let (modifiers, key) = match s.strip_suffix('+') {
+            Some(rest) if rest.is_empty() || rest.ends_with('+') => (rest, "+"),
+            _ => match s.rsplit_once('+') {
+                Some((modifiers, key)) => (modifiers, key),
+                None => ("", s),
+            },
+        };
+
+        let mut result = Self {
+            code: KeyCode::Null,
+            shift: false,
+            ctrl: false,
+            alt: false,
+        };
+
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            match modifier.to_lowercase().as_str() {
+                "shift" => result.shift = true,
+                "ctrl" => result.ctrl = true,
+                "alt" => result.alt = true,
+                _ => return Err(format!("invalid modifier {modifier:?} in key {s:?}")),
+            }
+        }
+
+        let mut chars = key.chars();
+        result.code = match (chars.next(), chars.next()) {
+            (Some(c), None) => match c {
+                '↑' => KeyCode::Up,
+                '↓' => KeyCode::Down,
+                '←' => KeyCode::Left,
+                '→' => KeyCode::Right,
+                c if result.shift => KeyCode::Char(c.to_uppercase().next().unwrap_or(c)),
+                c => KeyCode::Char(c),
+            },
+            _ => match key.to_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "enter" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                name => match name.strip_prefix('f').map(|n| n.parse::<u8>()) {
+                    Some(Ok(n)) if (1..=24).contains(&n) => KeyCode::F(n),
+                    _ => return Err(format!("invalid key {key:?} in key {s:?}")),
+                },
+            },
+        };
+
+        Ok(result)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KeyBindingRepr {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+/// All key presses that trigger an action.
+///
+/// In the config file, this can be either a single key or a list of keys. An
+/// empty list disables the action.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "KeyBindingRepr")]
+pub struct KeyBinding(Vec<KeyPress>);
+
+impl TryFrom<KeyBindingRepr> for KeyBinding {
+    type Error = String;
+
+    fn try_from(repr: KeyBindingRepr) -> Result<Self, Self::Error> {
+        let keys = match repr {
+            KeyBindingRepr::Single(key) => vec![key],
+            KeyBindingRepr::Multiple(keys) => keys,
+        };
+        let presses = keys
+            .iter()
+            .map(|k| k.parse())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self(presses))
+    }
+}
+
+impl KeyBinding {
+    fn from_defaults(keys: &[&str]) -> Self {
+        let presses = keys
+            .iter()
+            .map(|k| k.parse().expect("default key binding is valid"))
+            .collect();
+        Self(presses)
+    }
+
+    pub fn matches(&self, event: &InputEvent) -> bool {
+        self.0.iter().any(|p| p.matches(event))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Describe two related bindings at once, for example `j/k, ↓/↑`.
+    ///
+    /// If the bindings don't line up nicely, they are shown one after another.
+    pub fn pair(&self, other: &Self) -> String {
+        if self.0.len() != other.0.len() {
+            return match (self.is_empty(), other.is_empty()) {
+                (false, true) => self.to_string(),
+                (true, false) => other.to_string(),
+                _ => format!("{self} / {other}"),
+            };
+        }
+
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .map(|(a, b)| {
+                if a.modifiers() == b.modifiers() {
+                    format!("{}{}/{}", a.modifiers(), a.key(), b.key())
+                } else {
+                    format!("{a}/{b}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let presses = self.0.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        write!(f, "{}", presses.join(", "))
+    }
+}
+
+macro_rules! key_group {
+    (
+        $( #[$attr:meta] )*
+        pub struct $name:ident {
+            $(
+                $( #[$field_attr:meta] )*
+                pub $field:ident = [ $( $default:literal ),* ],
+            )*
+        }
+    ) => {
+        $( #[$attr] )*
+        #[derive(Debug, Clone, Deserialize)]
+        #[serde(default)]
+        pub struct $name {
+            $(
+                $( #[$field_attr] )*
+                pub $field: KeyBinding,
+            )*
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self {
+                    $( $field: KeyBinding::from_defaults(&[ $( $default ),* ]), )*
+                }
+            }
+        }
+    };
+}
+
+key_group! {
+    /// Actions available everywhere.
+    pub struct General {
+        pub exit = ["ctrl+c"],
+        pub help = ["F1", "?"],
+        pub log = ["F12"],
+    }
+}
+
+key_group! {
+    /// Scrolling without moving the cursor.
+    pub struct Scroll {
+        pub up_line = ["ctrl+y"],
+        pub down_line = ["ctrl+e"],
+        pub up_half = ["ctrl+u"],
+        pub down_half = ["ctrl+d"],
+        pub up_full = ["ctrl+b", "pageup"],
+        pub down_full = ["ctrl+f", "pagedown"],
+        pub center_cursor = ["z"],
+    }
+}
+
+key_group! {
+    /// Moving the cursor in lists and message trees.
+    pub struct Cursor {
+        pub up = ["k", "up"],
+        pub down = ["j", "down"],
+        pub to_top = ["g", "home"],
+        pub to_bottom = ["G", "end"],
+        pub up_sibling = ["K", "ctrl+up"],
+        pub down_sibling = ["J", "ctrl+down"],
+        pub to_parent = ["p"],
+        pub to_root = ["P"],
+        pub older = ["h", "left"],
+        pub newer = ["l", "right"],
+        pub older_unseen = ["H", "ctrl+left"],
+        pub newer_unseen = ["L", "ctrl+right"],
+        pub older_mention = ["alt+h", "alt+left"],
+        pub newer_mention = ["alt+l", "alt+right"],
+    }
+}
+
+key_group! {
+    /// Actions on the messages of a room.
+    pub struct Tree {
+        pub fold = ["space"],
+        pub toggle_seen = ["s"],
+        pub mark_visible_seen = ["S"],
+        pub mark_older_seen = ["ctrl+s"],
+        pub reply = ["r"],
+        pub reply_alternate = ["R"],
+        pub new_thread = ["t", "T"],
+        pub thread = ["v"],
+        pub subtree = ["V"],
+        pub flat = ["f"],
+    }
+}
+
+key_group! {
+    /// Actions in the rooms list.
+    pub struct Rooms {
+        pub enter = ["enter"],
+        pub connect = ["c"],
+        pub connect_all = ["C"],
+        pub disconnect = ["d"],
+        pub disconnect_all = ["D"],
+        pub connect_autojoin = ["a"],
+        pub disconnect_non_autojoin = ["A"],
+        pub new = ["n"],
+        pub delete = ["X"],
+        pub change_sort_order = ["s"],
+    }
+}
+
+key_group! {
+    /// Actions inside a room.
+    pub struct Room {
+        pub authenticate = ["a"],
+        pub nick = ["n", "N"],
+        pub more_messages = ["m"],
+        pub download_history = ["M"],
+        pub verify_history = ["ctrl+r"],
+        pub account = ["A"],
+        pub edit_message = ["e"],
+        pub delete_message = ["x", "delete"],
+        pub bans = ["B"],
+        pub inspect = ["i"],
+        pub links = ["I"],
+        pub search = ["/"],
+        pub emoji = ["ctrl+o"],
+        pub ignores = ["ctrl+x"],
+        pub present = ["ctrl+p"],
+        pub focus = ["tab"],
+        pub pm = ["p"],
+        pub ignore = ["m"],
+        pub moderate = ["o"],
+        pub ignore_nick = ["n"],
+        pub ignore_user_id = ["u"],
+        pub unban_user_id = ["n"],
+        pub search_query = ["/"],
+        pub search_more = ["m"],
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Keys {
+    pub general: General,
+    pub scroll: Scroll,
+    pub cursor: Cursor,
+    pub tree: Tree,
+    pub rooms: Rooms,
+    pub room: Room,
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+    use crate::ui::InputEvent;
+
+    use super::KeyPress;
+
+    fn press(s: &str) -> KeyPress {
+        s.parse().unwrap()
+    }
+
+    fn event(code: KeyCode, shift: bool, ctrl: bool, alt: bool) -> InputEvent {
+        let mut modifiers = KeyModifiers::NONE;
+        modifiers.set(KeyModifiers::SHIFT, shift);
+        modifiers.set(KeyModifiers::CONTROL, ctrl);
+        modifiers.set(KeyModifiers::ALT, alt);
+        InputEvent::from_event(Event::Key(KeyEvent::new(code, modifiers))).unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(press("a").code, KeyCode::Char('a'));
+        assert_eq!(press("space").code, KeyCode::Char(' '));
+        assert_eq!(press("+").code, KeyCode::Char('+'));
+        assert_eq!(press("Enter").code, KeyCode::Enter);
+        assert_eq!(press("pgdn").code, KeyCode::PageDown);
+        assert_eq!(press("up").code, KeyCode::Up);
+        assert_eq!(press("↑").code, KeyCode::Up);
+        assert_eq!(press("F1").code, KeyCode::F(1));
+        assert_eq!(press("f24").code, KeyCode::F(24));
+
+        let ctrl_plus = press("ctrl++");
+        assert_eq!(ctrl_plus.code, KeyCode::Char('+'));
+        assert!(ctrl_plus.ctrl);
+
+        let mods = press("Ctrl+alt+shift+left");
+        assert_eq!(mods.code, KeyCode::Left);
+        assert!(mods.ctrl && mods.alt && mods.shift);
+
+        // Shift is folded into the character.
+        assert_eq!(press("shift+a").code, KeyCode::Char('A'));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!("".parse::<KeyPress>().is_err());
+        assert!("foo".parse::<KeyPress>().is_err());
+        assert!("f0".parse::<KeyPress>().is_err());
+        assert!("f25".parse::<KeyPress>().is_err());
+        assert!("hyper+a".parse::<KeyPress>().is_err());
+        assert!("ctrl+".parse::<KeyPress>().is_err());
+    }
+
+    #[test]
+    fn matches() {
+        assert!(press("a").matches(&event(KeyCode::Char('a'), false, false, false)));
+        assert!(!press("a").matches(&event(KeyCode::Char('a'), false, true, false)));
+        assert!(!press("a").matches(&event(KeyCode::Char('A'), true, false, false)));
+
+        // Terminals may or may not report shift for characters.
+        assert!(press("A").matches(&event(KeyCode::Char('A'), true, false, false)));
+        assert!(press("A").matches(&event(KeyCode::Char('A'), false, false, false)));
+        assert!(press("shift+a").matches(&event(KeyCode::Char('A'), true, false, false)));
+
+        assert!(press("shift+tab").matches(&event(KeyCode::Tab, true, false, false)));
+        assert!(!press("tab").matches(&event(KeyCode::Tab, true, false, false)));
+        assert!(press("ctrl+c").matches(&event(KeyCode::Char('c'), false, true, false)));
+        assert!(!press("ctrl+c").matches(&event(KeyCode::Char('c'), false, true, true)));
+        assert!(!press("a").matches(&InputEvent::Paste("a".to_string())));
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for s in [
+            "a",
+            "A",
+            "+",
+            "ctrl++",
+            "space",
+            "ctrl+c",
+            "alt+h",
+            "ctrl+alt+x",
+            "shift+tab",
+            "ctrl+↑",
+            "enter",
+            "pagedown",
+            "F12",
+        ] {
+            let key = press(s);
+            assert_eq!(key.to_string(), s);
+            assert_eq!(press(&key.to_string()), key);
+        }
+
+        assert_eq!(press("up").to_string(), "↑");
+        assert_eq!(press("ESCAPE").to_string(), "esc");
+        assert_eq!(press("shift+a").to_string(), "A");
+    }
+}
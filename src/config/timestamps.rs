@@ -0,0 +1,122 @@
+//! Converting timestamps to the configured time zone and formatting them.
+
+use serde::Deserialize;
+use time::format_description::{self, FormatItem, OwnedFormatItem};
+use time::macros::{datetime, format_description};
+use time::{OffsetDateTime, UtcOffset};
+use unicode_width::UnicodeWidthStr;
+
+/// The time zone that timestamps are displayed in.
+///
+/// In the config file, this can be `"local"` for the system's time zone, a
+/// fixed offset like `"+02:00"` or the name of a time zone from the IANA time
+/// zone database like `"Europe/Berlin"`. The database is bundled with cove, so
+/// names work even if the system has no time zone information installed.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub enum TimeZone {
+    Fixed(UtcOffset),
+    Bundled(tz::TimeZoneRef<'static>),
+    Local(tz::TimeZone),
+}
+
+impl Default for TimeZone {
+    fn default() -> Self {
+        Self::Fixed(UtcOffset::UTC)
+    }
+}
+
+const OFFSET_FORMAT: &[FormatItem<'_>] =
+    format_description!("[offset_hour sign:mandatory]:[offset_minute]");
+const OFFSET_FORMAT_SHORT: &[FormatItem<'_>] = format_description!("[offset_hour sign:mandatory]");
+
+impl TryFrom<String> for TimeZone {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        if name == "local" {
+            return tz::TimeZone::local()
+                .map(Self::Local)
+                .map_err(|err| format!("failed to load local time zone: {err}"));
+        }
+
+        if name.starts_with(['+', '-']) {
+            return UtcOffset::parse(&name, OFFSET_FORMAT)
+                .or_else(|_| UtcOffset::parse(&name, OFFSET_FORMAT_SHORT))
+                .map(Self::Fixed)
+                .map_err(|_| format!("invalid time zone offset {name:?}"));
+        }
+
+        match tzdb::tz_by_name(&name) {
+            Some(tz) => Ok(Self::Bundled(tz)),
+            None => Err(format!("unknown time zone {name:?}")),
+        }
+    }
+}
+
+impl TimeZone {
+    fn offset_at(&self, time: OffsetDateTime) -> UtcOffset {
+        let tz = match self {
+            Self::Fixed(offset) => return *offset,
+            Self::Bundled(tz) => *tz,
+            Self::Local(tz) => tz.as_ref(),
+        };
+        tz.find_local_time_type(time.unix_timestamp())
+            .ok()
+            .and_then(|t| UtcOffset::from_whole_seconds(t.ut_offset()).ok())
+            .unwrap_or(UtcOffset::UTC)
+    }
+
+    /// Convert a timestamp to this time zone.
+    pub fn convert(&self, time: OffsetDateTime) -> OffsetDateTime {
+        time.to_offset(self.offset_at(time))
+    }
+}
+
+/// A format description as understood by the [`time`] crate, for example
+/// `"[year]-[month]-[day] [hour]:[minute]"`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct TimeFormat(OwnedFormatItem);
+
+impl TryFrom<String> for TimeFormat {
+    type Error = String;
+
+    fn try_from(format: String) -> Result<Self, Self::Error> {
+        format_description::parse_owned::<1>(&format)
+            .map(Self)
+            .map_err(|err| format!("invalid time format {format:?}: {err}"))
+    }
+}
+
+/// How timestamps are displayed throughout cove.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Timestamps {
+    #[serde(default)]
+    pub time_zone: TimeZone,
+    pub time_format: Option<TimeFormat>,
+}
+
+impl Timestamps {
+    /// Format a timestamp in the configured time zone.
+    ///
+    /// The `default` format is used if no format was configured. Different
+    /// places use different defaults, for example depending on whether they
+    /// have room for seconds.
+    pub fn format(&self, time: OffsetDateTime, default: &[FormatItem<'_>]) -> String {
+        let time = self.time_zone.convert(time);
+        let result = match &self.time_format {
+            Some(TimeFormat(format)) => time.format(format),
+            None => time.format(default),
+        };
+        result.unwrap_or_else(|_| "<invalid time>".to_string())
+    }
+
+    /// The width a formatted timestamp usually takes up on screen.
+    ///
+    /// Used to leave a gap of the same width where no timestamp is shown.
+    pub fn width(&self, default: &[FormatItem<'_>]) -> usize {
+        self.format(datetime!(2000-12-31 23:59:59 UTC), default)
+            .width()
+    }
+}
@@ -0,0 +1,60 @@
+//! Parsing durations like `90s`, `30m` or `12h` entered by the user.
+
+/// Parse a duration like `90s`, `30m`, `12h`, `7d` or `2w` into seconds.
+///
+/// A number without unit is interpreted as seconds. Returns `None` if the text
+/// is not a valid duration or the number of seconds doesn't fit into an `i64`.
+pub fn parse_seconds(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let (amount, factor) = match text.char_indices().last()? {
+        (i, 's') => (&text[..i], 1),
+        (i, 'm') => (&text[..i], 60),
+        (i, 'h') => (&text[..i], 60 * 60),
+        (i, 'd') => (&text[..i], 60 * 60 * 24),
+        (i, 'w') => (&text[..i], 60 * 60 * 24 * 7),
+        _ => (text, 1),
+    };
+    let amount = amount.trim();
+    if amount.is_empty() || !amount.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    amount.parse::<i64>().ok()?.checked_mul(factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_seconds;
+
+    #[test]
+    fn units() {
+        assert_eq!(parse_seconds("90s"), Some(90));
+        assert_eq!(parse_seconds("30m"), Some(30 * 60));
+        assert_eq!(parse_seconds("12h"), Some(12 * 60 * 60));
+        assert_eq!(parse_seconds("7d"), Some(7 * 24 * 60 * 60));
+        assert_eq!(parse_seconds("2w"), Some(2 * 7 * 24 * 60 * 60));
+    }
+
+    #[test]
+    fn without_unit() {
+        assert_eq!(parse_seconds("42"), Some(42));
+        assert_eq!(parse_seconds(" 5 m "), Some(5 * 60));
+        assert_eq!(parse_seconds("0"), Some(0));
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(parse_seconds(""), None);
+        assert_eq!(parse_seconds("m"), None);
+        assert_eq!(parse_seconds("-5m"), None);
+        assert_eq!(parse_seconds("+5m"), None);
+        assert_eq!(parse_seconds("5x"), None);
+        assert_eq!(parse_seconds("1.5h"), None);
+    }
+
+    #[test]
+    fn overflow() {
+        assert_eq!(parse_seconds("9223372036854775807s"), Some(i64::MAX));
+        assert_eq!(parse_seconds("9223372036854775807m"), None);
+        assert_eq!(parse_seconds("99999999999999999999"), None);
+    }
+}
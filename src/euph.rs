@@ -1,7 +1,10 @@
+mod ignore;
+mod notify;
 mod room;
 mod small_message;
 mod util;
 
+pub use ignore::*;
 pub use room::*;
 pub use small_message::*;
 pub use util::*;
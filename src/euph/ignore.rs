@@ -0,0 +1,157 @@
+use euphoxide::api::UserId;
+
+use super::small_message::normalize_nick;
+
+/// A user whose messages should be hidden in all rooms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ignore {
+    /// A specific user, regardless of their nick.
+    UserId(UserId),
+    /// Every user whose nick matches a pattern. A `*` in the pattern matches
+    /// any amount of chars.
+    Nick(String),
+}
+
+/// Match a nick against a pattern where `*` matches any amount of chars.
+///
+/// When a char doesn't match, only the most recent `*` needs to consume one
+/// more char. Earlier `*`s can't lead to a match that the most recent one
+/// can't find, so there is no need to backtrack any further.
+fn pattern_matches(pattern: &[char], nick: &[char]) -> bool {
+    let mut p = 0;
+    let mut n = 0;
+    // Position of the most recent `*` and of the nick char it was tried at
+    let mut star = None;
+
+    while n < nick.len() {
+        if pattern.get(p) == Some(&'*') {
+            star = Some((p, n));
+            p += 1;
+        } else if pattern.get(p) == Some(&nick[n]) {
+            p += 1;
+            n += 1;
+        } else if let Some((star_p, star_n)) = star {
+            star = Some((star_p, star_n + 1));
+            p = star_p + 1;
+            n = star_n + 1;
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Ignores prepared for checking many messages against them.
+///
+/// Nicks are normalized like for mentions before matching, so patterns are
+/// case-insensitive and ignore most punctuation.
+#[derive(Debug, Default)]
+pub struct IgnoreList {
+    user_ids: Vec<UserId>,
+    patterns: Vec<Vec<char>>,
+}
+
+impl IgnoreList {
+    pub fn new(ignores: &[Ignore]) -> Self {
+        let mut result = Self::default();
+        for ignore in ignores {
+            match ignore {
+                Ignore::UserId(id) => result.user_ids.push(id.clone()),
+                Ignore::Nick(pattern) => result
+                    .patterns
+                    .push(normalize_nick(pattern).chars().collect()),
+            }
+        }
+        result
+    }
+
+    /// Whether messages by a user should be hidden.
+    pub fn matches(&self, user_id: &UserId, nick: &str) -> bool {
+        if self.user_ids.contains(user_id) {
+            return true;
+        }
+        if self.patterns.is_empty() {
+            return false;
+        }
+
+        let nick = normalize_nick(nick).chars().collect::<Vec<_>>();
+        self.patterns
+            .iter()
+            .any(|pattern| pattern_matches(pattern, &nick))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use euphoxide::api::UserId;
+
+    use super::{pattern_matches, Ignore, IgnoreList};
+
+    fn matches(pattern: &str, nick: &str) -> bool {
+        let pattern = pattern.chars().collect::<Vec<_>>();
+        let nick = nick.chars().collect::<Vec<_>>();
+        pattern_matches(&pattern, &nick)
+    }
+
+    #[test]
+    fn literal() {
+        assert!(matches("foo", "foo"));
+        assert!(!matches("foo", "fo"));
+        assert!(!matches("foo", "fooo"));
+        assert!(!matches("foo", "bar"));
+    }
+
+    #[test]
+    fn stars() {
+        assert!(matches("*bot", "bot"));
+        assert!(matches("*bot", "chatbot"));
+        assert!(!matches("*bot", "bots"));
+
+        assert!(matches("bot*", "bot"));
+        assert!(matches("bot*", "botany"));
+        assert!(!matches("bot*", "robot"));
+
+        assert!(matches("*bot*", "robots"));
+        assert!(matches("a*b*c", "abc"));
+        assert!(matches("a*b*c", "aXbYbZc"));
+        assert!(!matches("a*b*c", "aXbYcZ"));
+
+        // Backtracking to the most recent star has to be enough.
+        assert!(matches("*ab*ab", "aabab"));
+        assert!(matches("*aab", "aaaab"));
+        assert!(!matches("*aab", "aaaba"));
+    }
+
+    #[test]
+    fn consecutive_stars() {
+        assert!(matches("**", ""));
+        assert!(matches("a**b", "ab"));
+        assert!(matches("a**b", "axxb"));
+        assert!(!matches("a**b", "axxc"));
+    }
+
+    #[test]
+    fn empty() {
+        assert!(matches("", ""));
+        assert!(!matches("", "a"));
+        assert!(matches("*", ""));
+        assert!(matches("*", "anything"));
+        assert!(!matches("a", ""));
+        assert!(!matches("a*", ""));
+    }
+
+    #[test]
+    fn normalized() {
+        let id = UserId("agent:abc".to_string());
+        let list = IgnoreList::new(&[Ignore::Nick("Foo Bar*".to_string())]);
+        assert!(list.matches(&id, "foobar"));
+        assert!(list.matches(&id, "FOO BAR!"));
+        assert!(list.matches(&id, "foo.bar, the second"));
+        assert!(!list.matches(&id, "foo baz"));
+
+        let list = IgnoreList::new(&[Ignore::UserId(id.clone())]);
+        assert!(list.matches(&id, "anyone"));
+        assert!(!list.matches(&UserId("agent:def".to_string()), "anyone"));
+    }
+}
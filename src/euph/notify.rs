@@ -0,0 +1,88 @@
+//! Running the external notification command.
+
+use std::process::Stdio;
+
+use euphoxide::api::Message;
+use log::{debug, warn};
+use tokio::process::Command;
+
+/// Replace the placeholders in an argument of the notification command.
+///
+/// Supported placeholders are `%r` (room name), `%n` (sender nick), `%c`
+/// (message content) and `%i` (message id). A literal `%` can be written as
+/// `%%`.
+fn format_arg(arg: &str, room: &str, msg: &Message) -> String {
+    let mut result = String::new();
+
+    let mut special = false;
+    for char in arg.chars() {
+        if special {
+            match char {
+                'r' => result.push_str(room),
+                'n' => result.push_str(&msg.sender.name),
+                'c' => result.push_str(&msg.content),
+                'i' => result.push_str(&msg.id.0.to_string()),
+                '%' => result.push('%'),
+                _ => {
+                    result.push('%');
+                    result.push(char);
+                }
+            }
+            special = false;
+        } else if char == '%' {
+            special = true;
+        } else {
+            result.push(char);
+        }
+    }
+
+    result
+}
+
+/// Spawn the notification command for a message.
+///
+/// The first element of `command` is the program, the remaining elements are
+/// its arguments. In addition to the placeholders in the arguments, the message
+/// is also available via the environment variables `COVE_ROOM`, `COVE_NICK`,
+/// `COVE_CONTENT` and `COVE_MSG_ID`.
+pub fn notify(command: &[String], room: &str, msg: &Message) {
+    let (program, args) = match command.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+
+    debug!("{room}: running notification command for {}", msg.id.0);
+
+    let child = Command::new(program)
+        .args(args.iter().map(|arg| format_arg(arg, room, msg)))
+        .env("COVE_ROOM", room)
+        .env("COVE_NICK", &msg.sender.name)
+        .env("COVE_CONTENT", &msg.content)
+        .env("COVE_MSG_ID", msg.id.0.to_string())
+        // The command must not interfere with the terminal ui.
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => {
+            warn!("{room}: failed to run notification command: {err}");
+            return;
+        }
+    };
+
+    // Wait for the command in the background so it doesn't linger as a zombie
+    // process after exiting.
+    let room = room.to_string();
+    tokio::task::spawn(async move {
+        match child.wait().await {
+            Ok(status) if !status.success() => {
+                warn!("{room}: notification command exited with {status}");
+            }
+            Ok(_) => {}
+            Err(err) => warn!("{room}: failed to wait for notification command: {err}"),
+        }
+    });
+}
@@ -1,12 +1,15 @@
 // TODO Stop if room does not exist (e. g. 404)
 
+use std::collections::HashSet;
 use std::convert::Infallible;
 use std::time::Duration;
 
 use euphoxide::api::packet::ParsedPacket;
 use euphoxide::api::{
-    Auth, AuthOption, Data, Log, Login, Logout, MessageId, Nick, Send, SendEvent, SendReply, Time,
-    UserId,
+    AccountId, Auth, AuthOption, Ban, Data, EditMessage, EditMessageEvent, EditMessageReply,
+    GetMessage, GetMessageReply, GrantAccess, GrantManager, Log, Login, Logout, Message, MessageId,
+    Nick, PacketType, PmInitiate, RevokeAccess, RevokeManager, Send, SendEvent, SendReply,
+    Snowflake, Time, Unban, UserId,
 };
 use euphoxide::bot::instance::{Event, Instance, InstanceConfig, Snapshot};
 use euphoxide::conn::{self, ConnTx};
@@ -14,10 +17,15 @@ use log::{debug, error, info, warn};
 use tokio::select;
 use tokio::sync::oneshot;
 
+use crate::config::Notify;
 use crate::macros::{logging_unwrap, ok_or_return};
 use crate::vault::EuphRoomVault;
 
+use super::{notify, small_message};
+
 const LOG_INTERVAL: Duration = Duration::from_secs(10);
+const LOG_AMOUNT: usize = 1000;
+const UNTRUNCATE_INTERVAL: Duration = Duration::from_secs(10);
 
 #[derive(Debug)]
 pub enum State {
@@ -37,6 +45,24 @@ impl State {
     }
 }
 
+/// Progress of downloading the full room history.
+#[derive(Debug, Clone, Default)]
+pub struct SyncProgress {
+    /// Amount of messages downloaded so far.
+    pub msgs: usize,
+    /// Time of the oldest message downloaded so far.
+    pub oldest: Option<Time>,
+    /// Whether already known messages are redownloaded to pick up edits and
+    /// deletions instead of downloading missing history.
+    pub verify: bool,
+    /// Where the next log request should start when verifying. `None` while
+    /// starting at the newest message.
+    verify_before: Option<MessageId>,
+    /// The `before` of the log request currently in flight, if any. Used to
+    /// tell our own log replies apart from those of other log requests.
+    requested: Option<Option<MessageId>>,
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("not connected to room")]
@@ -51,6 +77,9 @@ pub struct Room {
     instance: Instance,
     state: State,
 
+    notify: Notify,
+    notify_command: Option<&'static [String]>,
+
     /// `None` before any `snapshot-event`, then either `Some(None)` or
     /// `Some(Some(id))`. Reset whenever connection is lost.
     last_msg_id: Option<Option<MessageId>>,
@@ -58,10 +87,27 @@ pub struct Room {
     /// `Some` while `Self::regularly_request_logs` is running. Set to `None` to
     /// drop the sender and stop the task.
     log_request_canary: Option<oneshot::Sender<Infallible>>,
+
+    /// `Some` while `Self::regularly_untruncate_msgs` is running. Set to
+    /// `None` to drop the sender and stop the task.
+    untruncate_canary: Option<oneshot::Sender<Infallible>>,
+
+    /// `Some` while downloading the full room history.
+    sync: Option<SyncProgress>,
+
+    /// The `before` of the log request currently in flight while repairing
+    /// gaps in the room history, if any.
+    repair_requested: Option<MessageId>,
 }
 
 impl Room {
-    pub fn new<F>(vault: EuphRoomVault, instance_config: InstanceConfig, on_event: F) -> Self
+    pub fn new<F>(
+        vault: EuphRoomVault,
+        instance_config: InstanceConfig,
+        notify: Notify,
+        notify_command: Option<&'static [String]>,
+        on_event: F,
+    ) -> Self
     where
         F: Fn(Event) + std::marker::Send + Sync + 'static,
     {
@@ -76,8 +122,13 @@ impl Room {
             ephemeral,
             instance: instance_config.build(on_event),
             state: State::Disconnected,
+            notify,
+            notify_command,
             last_msg_id: None,
             log_request_canary: None,
+            untruncate_canary: None,
+            sync: None,
+            repair_requested: None,
         }
     }
 
@@ -105,6 +156,7 @@ impl Room {
                 // Juuust to make sure
                 self.last_msg_id = None;
                 self.log_request_canary = None;
+                self.untruncate_canary = None;
             }
             Event::Connected(_, Snapshot { conn_tx, state }) => {
                 if !self.ephemeral {
@@ -121,6 +173,18 @@ impl Room {
                     });
                 }
 
+                let (tx, rx) = oneshot::channel();
+                self.untruncate_canary = Some(tx);
+                let vault_clone = self.vault.clone();
+                let conn_tx_clone = conn_tx.clone();
+                debug!("{}: spawning untruncate task", self.instance.config().room);
+                tokio::task::spawn(async move {
+                    select! {
+                        _ = rx => {},
+                        _ = Self::regularly_untruncate_msgs(vault_clone, conn_tx_clone) => {},
+                    }
+                });
+
                 self.state = State::Connected(conn_tx, state);
 
                 let cookies = &*self.instance.config().server.cookies;
@@ -135,6 +199,7 @@ impl Room {
                 self.state = State::Disconnected;
                 self.last_msg_id = None;
                 self.log_request_canary = None;
+                self.untruncate_canary = None;
             }
             Event::Stopped(_) => {
                 // TODO Remove room somewhere if this happens? If it doesn't already happen during stabilization
@@ -150,24 +215,13 @@ impl Room {
         // sort of "repair logs" mode that can be started via some key binding.
         // For now, this is just a list of ideas.
         //
-        // Download room history until there are no more gaps between now and
-        // the first known message.
-        //
-        // Download room history until reaching the beginning of the room's
-        // history.
-        //
         // Check if the last known message still exists on the server. If it
         // doesn't, do a binary search to find the server's last message and
         // delete all older messages.
         //
-        // Untruncate messages in the history, as well as new messages.
-        //
         // Try to retrieve messages that are not in the room log by retrieving
         // them by id.
         //
-        // Redownload messages that are already known to find any edits and
-        // deletions that happened while the client was offline.
-        //
         // Delete messages marked as deleted as well as all their children.
 
         loop {
@@ -189,13 +243,109 @@ impl Room {
         // a certain point results in errors. By reducing the amount of messages
         // in each log request, we can get closer to this point. Since &rl2dev
         // is fairly low in activity, this should be fine.
-        let n = if vault.room() == "rl2dev" { 50 } else { 1000 };
+        let n = if vault.room() == "rl2dev" {
+            50
+        } else {
+            LOG_AMOUNT
+        };
 
         let _ = conn_tx.send(Log { n, before }).await;
         // The code handling incoming events and replies also handles
         // `LogReply`s, so we don't need to do anything special here.
     }
 
+    /// Fetch the full content of truncated messages, newest first.
+    ///
+    /// Messages that can't be fetched are not retried until the next
+    /// connection.
+    async fn regularly_untruncate_msgs(vault: EuphRoomVault, conn_tx: ConnTx) {
+        let mut failed = HashSet::new();
+        loop {
+            tokio::time::sleep(UNTRUNCATE_INTERVAL).await;
+            for id in logging_unwrap!(vault.truncated_msg_ids().await) {
+                if failed.contains(&id) {
+                    continue;
+                }
+                // The code handling incoming events and replies also handles
+                // `GetMessageReply`s, so we only need to wait for the reply
+                // to avoid flooding the server.
+                if conn_tx.send(GetMessage { id }).await.is_err() {
+                    failed.insert(id);
+                }
+            }
+        }
+    }
+
+    /// Request the next batch of logs while downloading the full room history,
+    /// or finish if the beginning of the room's history has been reached.
+    ///
+    /// Logs are always requested before the start of the latest span, so
+    /// downloading can be resumed after a restart without redownloading any
+    /// messages.
+    async fn sync_step(&mut self) {
+        let room_name = self.vault.room();
+        let Some(sync) = &mut self.sync else { return };
+        let Some(conn_tx) = self.state.conn_tx() else {
+            return;
+        };
+
+        let before = if sync.verify {
+            // Verification ends once all known messages have been redownloaded.
+            let oldest = logging_unwrap!(self.vault.oldest_msg_id().await);
+            match (sync.verify_before, oldest) {
+                (Some(before), Some(oldest)) if before <= oldest => {
+                    info!("{room_name}: verified room history");
+                    self.sync = None;
+                    return;
+                }
+                (before, _) => before,
+            }
+        } else {
+            match logging_unwrap!(self.vault.last_span().await) {
+                Some((None, _)) => {
+                    info!("{room_name}: downloaded full room history");
+                    self.sync = None;
+                    return;
+                }
+                Some((Some(before), _)) => Some(before),
+                None => None,
+            }
+        };
+
+        debug!("{room_name}: requesting logs to sync room history");
+        sync.requested = Some(before);
+        conn_tx.send_only(Log {
+            n: LOG_AMOUNT,
+            before,
+        });
+    }
+
+    /// Request logs for the newest gap between the spans of known messages.
+    ///
+    /// Each log reply shrinks the gap from its upper end until it is closed
+    /// and the next gap can be repaired.
+    async fn repair_step(&mut self) {
+        self.repair_requested = None;
+        if self.ephemeral {
+            return;
+        }
+        let Some(conn_tx) = self.state.conn_tx() else {
+            return;
+        };
+
+        let gaps = logging_unwrap!(self.vault.gaps().await);
+        let Some((_, after)) = gaps.last() else {
+            return;
+        };
+
+        debug!("{}: requesting logs to repair gap", self.vault.room());
+        self.repair_requested = Some(*after);
+        conn_tx.send_only(Log {
+            n: LOG_AMOUNT,
+            before: Some(*after),
+        });
+    }
+
     fn own_user_id(&self) -> Option<UserId> {
         if let State::Connected(_, state) = &self.state {
             Some(match state {
@@ -207,8 +357,59 @@ impl Room {
         }
     }
 
+    fn own_nick(&self) -> Option<&str> {
+        if let State::Connected(_, state) = &self.state {
+            Some(match state {
+                conn::State::Joining(joining) => &joining.hello.as_ref()?.session.name,
+                conn::State::Joined(joined) => &joined.session.name,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Run the notification command if the room's notification rule applies
+    /// to a newly arrived message.
+    async fn notify(&self, msg: &Message) {
+        let command = match self.notify_command {
+            Some(command) => command,
+            None => return,
+        };
+
+        if self.own_user_id().as_ref() == Some(&msg.sender.id) {
+            return;
+        }
+
+        let ignores = logging_unwrap!(self.vault.vault().ignore_list().await);
+        if ignores.matches(&msg.sender.id, &msg.sender.name) {
+            return;
+        }
+
+        let notify = match self.notify {
+            Notify::Off => false,
+            Notify::Mentions => match self.own_nick() {
+                Some(nick) => small_message::mentions_nick(&msg.content, nick),
+                None => false,
+            },
+            Notify::All => true,
+        };
+
+        if notify {
+            notify::notify(command, &self.instance.config().room, msg);
+        }
+    }
+
     async fn on_packet(&mut self, packet: ParsedPacket) {
         let room_name = &self.instance.config().room;
+
+        if let (PacketType::LogReply, Err(reason)) = (packet.r#type, &packet.content) {
+            if self.sync.is_some() {
+                warn!("{room_name}: stopped downloading room history: {reason}");
+                self.sync = None;
+            }
+            self.repair_requested = None;
+        }
+
         let data = ok_or_return!(&packet.content);
         match data {
             Data::BounceEvent(_) => {}
@@ -225,21 +426,26 @@ impl Room {
             Data::NickEvent(d) => {
                 debug!("{room_name}: {:?} renamed to {:?}", d.from, d.to);
             }
-            Data::EditMessageEvent(_) => {
-                info!("{room_name}: a message was edited");
+            Data::EditMessageEvent(EditMessageEvent { message, .. })
+            | Data::EditMessageReply(EditMessageReply { message, .. }) => {
+                debug!("{room_name}: message {} was edited", message.id.0);
+                logging_unwrap!(self.vault.update_msg(Box::new(message.clone())).await);
             }
             Data::PartEvent(d) => {
                 debug!("{room_name}: {:?} left", d.0.name);
             }
             Data::PingEvent(_) => {}
             Data::PmInitiateEvent(d) => {
-                // TODO Show info popup and automatically join PM room
                 info!(
                     "{room_name}: {:?} initiated a pm from &{}",
                     d.from_nick, d.from_room
                 );
             }
             Data::SendEvent(SendEvent(msg)) | Data::SendReply(SendReply(msg)) => {
+                if let Data::SendEvent(_) = data {
+                    self.notify(msg).await;
+                }
+
                 let own_user_id = self.own_user_id();
                 if let Some(last_msg_id) = &mut self.last_msg_id {
                     logging_unwrap!(
@@ -259,6 +465,12 @@ impl Room {
                         .add_msgs(d.log.clone(), None, self.own_user_id())
                         .await
                 );
+                // Any log request from a previous connection is lost.
+                self.sync_step().await;
+                self.repair_step().await;
+            }
+            Data::GetMessageReply(GetMessageReply(msg)) => {
+                logging_unwrap!(self.vault.update_msg(Box::new(msg.clone())).await);
             }
             Data::LogReply(d) => {
                 logging_unwrap!(
@@ -266,6 +478,37 @@ impl Room {
                         .add_msgs(d.log.clone(), d.before, self.own_user_id())
                         .await
                 );
+                if let Some(sync) = &mut self.sync {
+                    if sync.requested == Some(d.before) {
+                        sync.msgs += d.log.len();
+                        if let Some(msg) = d.log.first() {
+                            sync.oldest = Some(msg.time);
+                        }
+                        if sync.verify {
+                            match (d.log.first(), d.log.last()) {
+                                (Some(first), Some(last)) => {
+                                    // Known messages missing from the reply are
+                                    // only flagged. Deletions are taken from the
+                                    // messages themselves and from edit events.
+                                    let ids = d.log.iter().map(|m| m.id).collect::<Vec<_>>();
+                                    let end = d.before.unwrap_or(last.id);
+                                    logging_unwrap!(
+                                        self.vault.mark_missing(first.id, end, ids).await
+                                    );
+                                    sync.verify_before = Some(first.id);
+                                }
+                                _ => {
+                                    info!("{room_name}: verified room history");
+                                    self.sync = None;
+                                }
+                            }
+                        }
+                        self.sync_step().await;
+                    }
+                }
+                if self.repair_requested.is_some() && self.repair_requested == d.before {
+                    self.repair_step().await;
+                }
             }
             _ => {}
         }
@@ -286,11 +529,52 @@ impl Room {
         Ok(())
     }
 
+    /// Whether the full room history is currently being downloaded.
+    pub fn sync_progress(&self) -> Option<&SyncProgress> {
+        self.sync.as_ref()
+    }
+
+    /// Start downloading the full room history. If `verify` is set, all
+    /// known messages are redownloaded instead to pick up edits and deletions.
+    ///
+    /// Returns `false` if the history can't be downloaded because the room is
+    /// ephemeral.
+    pub async fn start_sync(&mut self, verify: bool) -> bool {
+        if self.ephemeral {
+            return false;
+        }
+        if self.sync.is_none() {
+            self.sync = Some(SyncProgress {
+                verify,
+                ..SyncProgress::default()
+            });
+            if let State::Connected(_, conn::State::Joined(_)) = &self.state {
+                self.sync_step().await;
+            }
+        }
+        true
+    }
+
+    pub fn stop_sync(&mut self) {
+        self.sync = None;
+    }
+
+    /// Fetch the full content of a truncated message.
+    pub fn untruncate(&self, id: MessageId) -> Result<(), Error> {
+        self.conn_tx()?.send_only(GetMessage { id });
+        Ok(())
+    }
+
     pub fn nick(&self, name: String) -> Result<(), Error> {
         self.conn_tx()?.send_only(Nick { name });
         Ok(())
     }
 
+    pub fn pm_initiate(&self, user_id: UserId) -> Result<(), Error> {
+        self.conn_tx()?.send_only(PmInitiate { user_id });
+        Ok(())
+    }
+
     pub fn send(
         &self,
         parent: Option<MessageId>,
@@ -306,6 +590,76 @@ impl Room {
         Ok(rx)
     }
 
+    /// Replace a message's content. Requires manager permissions.
+    pub fn edit_message(&self, msg: &Message, content: String) -> Result<(), Error> {
+        self.conn_tx()?.send_only(EditMessage {
+            id: msg.id,
+            // Messages that were never edited have a previous edit id of zero.
+            previous_edit_id: msg.previous_edit_id.unwrap_or(Snowflake(0)),
+            parent: msg.parent,
+            content: Some(content),
+            delete: false,
+            announce: true,
+        });
+        Ok(())
+    }
+
+    /// Delete a message. Requires manager permissions.
+    pub fn delete_message(&self, msg: &Message) -> Result<(), Error> {
+        self.conn_tx()?.send_only(EditMessage {
+            id: msg.id,
+            previous_edit_id: msg.previous_edit_id.unwrap_or(Snowflake(0)),
+            parent: msg.parent,
+            content: None,
+            delete: true,
+            announce: true,
+        });
+        Ok(())
+    }
+
+    /// Ban a user from the room, either permanently or for a number of
+    /// seconds. Requires manager permissions.
+    pub fn ban(&self, id: UserId, seconds: Option<i32>) -> Result<(), Error> {
+        self.conn_tx()?.send_only(Ban { id, seconds });
+        Ok(())
+    }
+
+    /// Requires manager permissions.
+    pub fn unban(&self, id: UserId) -> Result<(), Error> {
+        self.conn_tx()?.send_only(Unban { id });
+        Ok(())
+    }
+
+    /// Allow an account to join the private room. Requires manager permissions.
+    pub fn grant_access(&self, account_id: AccountId) -> Result<(), Error> {
+        self.conn_tx()?.send_only(GrantAccess {
+            account_id: Some(account_id),
+            passcode: None,
+        });
+        Ok(())
+    }
+
+    /// Requires manager permissions.
+    pub fn revoke_access(&self, account_id: AccountId) -> Result<(), Error> {
+        self.conn_tx()?.send_only(RevokeAccess {
+            account_id: Some(account_id),
+            passcode: None,
+        });
+        Ok(())
+    }
+
+    /// Requires manager permissions.
+    pub fn grant_manager(&self, account_id: AccountId) -> Result<(), Error> {
+        self.conn_tx()?.send_only(GrantManager { account_id });
+        Ok(())
+    }
+
+    /// Requires manager permissions.
+    pub fn revoke_manager(&self, account_id: AccountId) -> Result<(), Error> {
+        self.conn_tx()?.send_only(RevokeManager { account_id });
+        Ok(())
+    }
+
     pub fn login(&self, email: String, password: String) -> Result<(), Error> {
         self.conn_tx()?.send_only(Login {
             namespace: "email".to_string(),
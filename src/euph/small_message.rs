@@ -1,4 +1,7 @@
+use std::iter::Peekable;
 use std::mem;
+use std::ops::Range;
+use std::str::CharIndices;
 
 use crossterm::style::{ContentStyle, Stylize};
 use euphoxide::api::{MessageId, Snowflake, Time};
@@ -10,7 +13,7 @@ use crate::ui::ChatMsg;
 
 use super::util;
 
-fn nick_char(ch: char) -> bool {
+pub fn nick_char(ch: char) -> bool {
     // Closely following the heim mention regex:
     // https://github.com/euphoria-io/heim/blob/978c921063e6b06012fc8d16d9fbf1b3a0be1191/client/lib/stores/chat.js#L14-L15
     // `>` has been experimentally confirmed to delimit mentions as well.
@@ -20,12 +23,89 @@ fn nick_char(ch: char) -> bool {
     }
 }
 
-fn room_char(ch: char) -> bool {
+pub fn room_char(ch: char) -> bool {
     // Basically just \w, see also
     // https://github.com/euphoria-io/heim/blob/978c921063e6b06012fc8d16d9fbf1b3a0be1191/client/lib/ui/MessageText.js#L66
     ch.is_ascii_alphanumeric() || ch == '_'
 }
 
+/// Normalize a nick for comparing it with mentions.
+///
+/// Characters that would end a mention are removed, so a nick is normalized the
+/// same way as the mention created by typing `@` followed by the nick.
+pub fn normalize_nick(nick: &str) -> String {
+    nick.chars()
+        .filter(|c| nick_char(*c))
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// The text following the `@` when mentioning a nick.
+pub fn mention(nick: &str) -> String {
+    nick.chars().filter(|c| nick_char(*c)).collect()
+}
+
+/// Whether a mention that has been typed so far could refer to a nick.
+pub fn mention_matches_nick(partial: &str, nick: &str) -> bool {
+    normalize_nick(nick).starts_with(&normalize_nick(partial))
+}
+
+/// Byte ranges of all mentions in a message's content, including the leading
+/// `@`. Mentions consisting of only the `@` are skipped.
+///
+/// Both the [`Highlighter`] and [`mentions_nick`] find mentions this way, so
+/// a message is highlighted exactly when its mentions are.
+struct Mentions<'a> {
+    content: &'a str,
+    chars: CharIndices<'a>,
+    mention_possible: bool,
+}
+
+impl<'a> Mentions<'a> {
+    fn new(content: &'a str) -> Self {
+        Self {
+            content,
+            chars: content.char_indices(),
+            mention_possible: true,
+        }
+    }
+}
+
+impl Iterator for Mentions<'_> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (start, char) = self.chars.next()?;
+            let possible = self.mention_possible;
+            self.mention_possible = !char.is_alphanumeric();
+            if char != '@' || !possible {
+                continue;
+            }
+
+            let mut end = self.content.len();
+            for (idx, char) in self.chars.by_ref() {
+                self.mention_possible = !char.is_alphanumeric();
+                if !nick_char(char) {
+                    end = idx;
+                    break;
+                }
+            }
+
+            if end - start > 1 {
+                return Some(start..end);
+            }
+        }
+    }
+}
+
+/// Whether a message's content mentions a nick.
+pub fn mentions_nick(content: &str, nick: &str) -> bool {
+    let nick = normalize_nick(nick);
+    !nick.is_empty()
+        && Mentions::new(content).any(|m| normalize_nick(&content[m.start + 1..m.end]) == nick)
+}
+
 enum Span {
     Nothing,
     Mention,
@@ -37,10 +117,16 @@ struct Highlighter<'a> {
     content: &'a str,
     base_style: ContentStyle,
     exact: bool,
+    /// Normalized, empty if mentions of our own nick should not be highlighted.
+    own_nick: String,
+
+    mentions: Peekable<Mentions<'a>>,
 
     span: Span,
     span_start: usize,
-    room_or_mention_possible: bool,
+    /// End of the current span if it is a mention.
+    mention_end: usize,
+    room_possible: bool,
 
     result: Styled,
 }
@@ -56,10 +142,15 @@ impl<'a> Highlighter<'a> {
         }
 
         let text = &self.content[self.span_start..idx]; // Includes @
+        let style = if !self.own_nick.is_empty() && normalize_nick(&text[1..]) == self.own_nick {
+            self.base_style.reverse()
+        } else {
+            self.base_style
+        };
         self.result = mem::take(&mut self.result).and_then(if self.exact {
-            util::style_nick_exact(text, self.base_style)
+            util::style_nick_exact(text, style)
         } else {
-            util::style_nick(text, self.base_style)
+            util::style_nick(text, style)
         });
 
         self.span = Span::Nothing;
@@ -121,9 +212,17 @@ impl<'a> Highlighter<'a> {
         self.span_start = idx;
     }
 
+    /// The mention starting at `idx`, if any.
+    fn mention_at(&mut self, idx: usize) -> Option<Range<usize>> {
+        while self.mentions.peek().filter(|m| m.start < idx).is_some() {
+            self.mentions.next();
+        }
+        self.mentions.next_if(|m| m.start == idx)
+    }
+
     fn close_span_before_current_char(&mut self, idx: usize, char: char) {
         match self.span {
-            Span::Mention if !nick_char(char) => self.close_mention(idx),
+            Span::Mention if idx == self.mention_end => self.close_mention(idx),
             Span::Room if !room_char(char) => self.close_room(idx),
             Span::Emoji if char == '&' || char == '@' => {
                 self.span = Span::Nothing;
@@ -133,12 +232,17 @@ impl<'a> Highlighter<'a> {
     }
 
     fn update_span_with_current_char(&mut self, idx: usize, char: char) {
-        match self.span {
-            Span::Nothing if char == '@' && self.room_or_mention_possible => {
+        if let Span::Nothing = self.span {
+            if let Some(mention) = self.mention_at(idx) {
                 self.close_plain(idx);
                 self.span = Span::Mention;
+                self.mention_end = mention.end;
+                return;
             }
-            Span::Nothing if char == '&' && self.room_or_mention_possible => {
+        }
+
+        match self.span {
+            Span::Nothing if char == '&' && self.room_possible => {
                 self.close_plain(idx);
                 self.span = Span::Room;
             }
@@ -174,21 +278,30 @@ impl<'a> Highlighter<'a> {
         self.update_span_with_current_char(idx, char);
 
         // More permissive than the heim web client
-        self.room_or_mention_possible = !char.is_alphanumeric();
+        self.room_possible = !char.is_alphanumeric();
     }
 
-    fn highlight(content: &'a str, base_style: ContentStyle, exact: bool) -> Styled {
+    fn highlight(
+        content: &'a str,
+        base_style: ContentStyle,
+        exact: bool,
+        own_nick: &str,
+    ) -> Styled {
+        let content = if exact { content } else { content.trim() };
         let mut this = Self {
-            content: if exact { content } else { content.trim() },
+            content,
             base_style,
             exact,
+            own_nick: normalize_nick(own_nick),
+            mentions: Mentions::new(content).peekable(),
             span: Span::Nothing,
             span_start: 0,
-            room_or_mention_possible: true,
+            mention_end: 0,
+            room_possible: true,
             result: Styled::default(),
         };
 
-        for (idx, char) in (if exact { content } else { content.trim() }).char_indices() {
+        for (idx, char) in content.char_indices() {
             this.step(idx, char);
         }
 
@@ -198,8 +311,13 @@ impl<'a> Highlighter<'a> {
     }
 }
 
-fn highlight_content(content: &str, base_style: ContentStyle, exact: bool) -> Styled {
-    Highlighter::highlight(content, base_style, exact)
+fn highlight_content(
+    content: &str,
+    base_style: ContentStyle,
+    exact: bool,
+    own_nick: &str,
+) -> Styled {
+    Highlighter::highlight(content, base_style, exact, own_nick)
 }
 
 #[derive(Debug, Clone)]
@@ -210,14 +328,23 @@ pub struct SmallMessage {
     pub nick: String,
     pub content: String,
     pub seen: bool,
+    /// Whether the message was deleted or is missing on the server.
+    pub deleted: bool,
+    /// Whether the sender is on the ignore list.
+    pub ignored: bool,
 }
 
 fn as_me(content: &str) -> Option<&str> {
     content.strip_prefix("/me")
 }
 
-fn style_me() -> ContentStyle {
-    ContentStyle::default().grey().italic()
+fn style_me(base: ContentStyle) -> ContentStyle {
+    base.grey().italic()
+}
+
+/// Base style of messages mentioning our own nick.
+fn style_mention() -> ContentStyle {
+    ContentStyle::default().on_dark_yellow()
 }
 
 fn styled_nick(nick: &str) -> Styled {
@@ -227,26 +354,54 @@ fn styled_nick(nick: &str) -> Styled {
 }
 
 fn styled_nick_me(nick: &str) -> Styled {
-    let style = style_me();
+    let style = style_me(ContentStyle::default());
     Styled::new("*", style).and_then(util::style_nick(nick, style))
 }
 
-fn styled_content(content: &str) -> Styled {
-    highlight_content(content.trim(), ContentStyle::default(), false)
+fn styled_content(content: &str, base: ContentStyle, own_nick: &str) -> Styled {
+    highlight_content(content.trim(), base, false, own_nick)
+}
+
+fn styled_content_me(content: &str, base: ContentStyle, own_nick: &str) -> Styled {
+    let style = style_me(base);
+    highlight_content(content.trim(), style, false, own_nick).then("*", style)
 }
 
-fn styled_content_me(content: &str) -> Styled {
-    let style = style_me();
-    highlight_content(content.trim(), style, false).then("*", style)
+fn styled_tombstone() -> Styled {
+    Styled::new("[deleted]", ContentStyle::default().dark_grey().italic())
+}
+
+fn styled_ignored() -> Styled {
+    Styled::new(
+        "[ignored message]",
+        ContentStyle::default().dark_grey().italic(),
+    )
 }
 
 fn styled_editor_content(content: &str) -> Styled {
     let style = if as_me(content).is_some() {
-        style_me()
+        style_me(ContentStyle::default())
+    } else {
+        ContentStyle::default()
+    };
+    highlight_content(content, style, true, "")
+}
+
+fn styled_msg(nick: &str, content: &str, own_nick: &str) -> (Styled, Styled) {
+    let base = if mentions_nick(content, own_nick) {
+        style_mention()
     } else {
         ContentStyle::default()
     };
-    highlight_content(content, style, true)
+
+    if let Some(content) = as_me(content) {
+        (
+            styled_nick_me(nick),
+            styled_content_me(content, base, own_nick),
+        )
+    } else {
+        (styled_nick(nick), styled_content(content, base, own_nick))
+    }
 }
 
 impl Msg for SmallMessage {
@@ -274,19 +429,21 @@ impl ChatMsg for SmallMessage {
         self.time.0
     }
 
-    fn styled(&self) -> (Styled, Styled) {
-        Self::pseudo(&self.nick, &self.content)
+    fn mentions(&self, nick: &str) -> bool {
+        !self.deleted && !self.ignored && mentions_nick(&self.content, nick)
     }
 
-    fn edit(nick: &str, content: &str) -> (Styled, Styled) {
-        (styled_nick(nick), styled_editor_content(content))
-    }
-
-    fn pseudo(nick: &str, content: &str) -> (Styled, Styled) {
-        if let Some(content) = as_me(content) {
-            (styled_nick_me(nick), styled_content_me(content))
+    fn styled(&self, own_nick: &str) -> (Styled, Styled) {
+        if self.deleted {
+            (styled_nick(&self.nick), styled_tombstone())
+        } else if self.ignored {
+            (styled_nick(&self.nick), styled_ignored())
         } else {
-            (styled_nick(nick), styled_content(content))
+            styled_msg(&self.nick, &self.content, own_nick)
         }
     }
+
+    fn edit(nick: &str, content: &str) -> (Styled, Styled) {
+        (styled_nick(nick), styled_editor_content(content))
+    }
 }
@@ -1,12 +1,21 @@
 //! Export logs from the vault to plain text files.
 
+mod html;
 mod json;
+mod markdown;
 mod text;
 
-use std::fs::File;
-use std::io::{self, BufWriter, Write};
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
 
-use crate::vault::{EuphRoomVault, EuphVault};
+use euphoxide::api::{MessageId, Time};
+use time::format_description::well_known::Rfc3339;
+use time::{Duration, OffsetDateTime};
+
+use crate::config::Timestamps;
+use crate::duration;
+use crate::vault::{EuphRoomVault, EuphVault, ExportFilter};
 
 #[derive(Debug, Clone, Copy, clap::ValueEnum)]
 pub enum Format {
@@ -16,6 +25,10 @@ pub enum Format {
     Json,
     /// Message objects in the same format as the euphoria API uses, one per line.
     JsonStream,
+    /// Self-contained web page with collapsible threads.
+    Html,
+    /// Threads as nested lists, grouped by day.
+    Markdown,
 }
 
 impl Format {
@@ -24,6 +37,8 @@ impl Format {
             Self::Text => "text",
             Self::Json => "json",
             Self::JsonStream => "json stream",
+            Self::Html => "html",
+            Self::Markdown => "markdown",
         }
     }
 
@@ -31,10 +46,32 @@ impl Format {
         match self {
             Self::Text => "txt",
             Self::Json | Self::JsonStream => "json",
+            Self::Html => "html",
+            Self::Markdown => "md",
         }
     }
 }
 
+/// Parse either an RFC 3339 timestamp or a time relative to now like `90s`,
+/// `30m`, `12h`, `7d` or `2w`.
+pub fn parse_time(s: &str) -> Result<OffsetDateTime, String> {
+    if let Ok(time) = OffsetDateTime::parse(s, &Rfc3339) {
+        return Ok(time);
+    }
+
+    let seconds = duration::parse_seconds(s)
+        .ok_or_else(|| format!("{s:?} is neither an RFC 3339 timestamp nor a relative time"))?;
+    OffsetDateTime::now_utc()
+        .checked_sub(Duration::seconds(seconds))
+        .ok_or_else(|| format!("{s:?} is too far in the past"))
+}
+
+fn parse_msg_id(s: &str) -> Result<MessageId, String> {
+    s.parse()
+        .map(MessageId)
+        .map_err(|_| format!("{s:?} is not a valid message id"))
+}
+
 #[derive(Debug, clap::Parser)]
 pub struct Args {
     rooms: Vec<String>,
@@ -64,26 +101,165 @@ pub struct Args {
     #[arg(long, short, default_value_t = Into::into("%r.%e"))]
     #[arg(verbatim_doc_comment)]
     out: String,
+
+    /// Only export messages sent at or after this time.
+    ///
+    /// Either an RFC 3339 timestamp or a time relative to now like `12h`, `7d`
+    /// or `2w`.
+    #[arg(long, value_parser = parse_time)]
+    since: Option<OffsetDateTime>,
+
+    /// Only export messages sent at or before this time.
+    ///
+    /// Either an RFC 3339 timestamp or a time relative to now like `12h`, `7d`
+    /// or `2w`.
+    #[arg(long, value_parser = parse_time)]
+    until: Option<OffsetDateTime>,
+
+    /// Only export messages sent by this nick (case insensitive).
+    #[arg(long, short)]
+    nick: Option<String>,
+
+    /// Only export this message and its replies.
+    #[arg(long, value_parser = parse_msg_id)]
+    thread: Option<MessageId>,
+
+    /// Leave out deleted messages and messages missing on the server.
+    #[arg(long)]
+    exclude_deleted: bool,
+
+    /// Append only the messages that are newer than the ones exported by the
+    /// previous incremental export to the same file.
+    ///
+    /// If the previous export was interrupted, anything it wrote after its last
+    /// checkpoint is exported again. If the file is missing or was truncated,
+    /// it is exported in full.
+    ///
+    /// Only supported by the `text` and `json-stream` formats. In the `text`
+    /// format, new replies to already exported messages are appended together
+    /// with the thread they belong to, showing the already exported messages
+    /// as `[...]`.
+    #[arg(long)]
+    incremental: bool,
+
+    /// Export the file of an incremental export in full again.
+    ///
+    /// Messages older than the ones already exported, for example from
+    /// downloading more history or importing logs, are only picked up this
+    /// way. Later incremental exports continue from the new file.
+    #[arg(long, requires = "incremental")]
+    rescan: bool,
+}
+
+impl Args {
+    fn filter(&self) -> ExportFilter {
+        ExportFilter {
+            since: self.since.map(Time),
+            until: self.until.map(Time),
+            nick: self.nick.clone(),
+            thread: self.thread,
+            exclude_deleted: self.exclude_deleted,
+            after: None,
+            up_to: None,
+        }
+    }
 }
 
 async fn export_room<W: Write>(
     vault: &EuphRoomVault,
     out: &mut W,
     format: Format,
+    timestamps: &Timestamps,
+    filter: &ExportFilter,
 ) -> anyhow::Result<()> {
     match format {
-        Format::Text => text::export(vault, out).await?,
-        Format::Json => json::export(vault, out).await?,
-        Format::JsonStream => json::export_stream(vault, out).await?,
+        Format::Text => text::export(vault, out, timestamps, filter).await?,
+        Format::Json => json::export(vault, out, filter).await?,
+        Format::JsonStream => json::export_stream(vault, out, filter).await?,
+        Format::Html => html::export(vault, out, timestamps, filter).await?,
+        Format::Markdown => markdown::export(vault, out, timestamps, filter).await?,
     }
     Ok(())
 }
 
-pub async fn export(vault: &EuphVault, mut args: Args) -> anyhow::Result<()> {
+async fn export_room_incremental(
+    vault: &EuphRoomVault,
+    out: &str,
+    format: Format,
+    timestamps: &Timestamps,
+    rescan: bool,
+    filter: &ExportFilter,
+) -> anyhow::Result<()> {
+    // Messages arriving during the export are left for the next one.
+    let Some(up_to) = vault.newest_msg_id().await? else {
+        eprintln!("  no messages");
+        return Ok(());
+    };
+
+    // The checkpoint shouldn't depend on the directory cove is run from.
+    let path = env::current_dir()?.join(out);
+    let key = path.to_string_lossy().to_string();
+    let checkpoint = vault.export_checkpoint(key.clone()).await?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&path)?;
+    let after = match checkpoint {
+        Some((last_id, len)) if !rescan && file.metadata()?.len() >= len => {
+            // Remove anything written by an interrupted export.
+            file.set_len(len)?;
+            Some(last_id)
+        }
+        _ => {
+            file.set_len(0)?;
+            None
+        }
+    };
+    if after >= Some(up_to) {
+        eprintln!("  no new messages");
+        return Ok(());
+    }
+    file.seek(SeekFrom::End(0))?;
+
+    let filter = ExportFilter {
+        after,
+        up_to: Some(up_to),
+        ..filter.clone()
+    };
+    let mut file = BufWriter::new(file);
+    if let Format::Text = format {
+        // Trees aren't exported in the order of their messages' ids, so there
+        // is only a checkpoint once all of them are written.
+        text::export(vault, &mut file, timestamps, &filter).await?;
+        file.flush()?;
+        let len = file.get_mut().stream_position()?;
+        vault.set_export_checkpoint(key, up_to, len).await?;
+    } else {
+        json::export_stream_incremental(vault, &mut file, key, filter).await?;
+    }
+    Ok(())
+}
+
+pub async fn export(
+    vault: &EuphVault,
+    mut args: Args,
+    timestamps: &Timestamps,
+) -> anyhow::Result<()> {
     if args.out.ends_with('/') {
         args.out.push_str("%r.%e");
     }
 
+    if args.incremental {
+        if !matches!(args.format, Format::Text | Format::JsonStream) {
+            anyhow::bail!("incremental exports only support the text and json-stream formats");
+        }
+        if args.out == "-" {
+            anyhow::bail!("incremental exports can't be written to stdout");
+        }
+    }
+
     let rooms = if args.all {
         let mut rooms = vault.rooms().await?;
         rooms.sort_unstable();
@@ -98,19 +274,33 @@ pub async fn export(vault: &EuphVault, mut args: Args) -> anyhow::Result<()> {
         eprintln!("No rooms to export");
     }
 
+    let filter = args.filter();
+
     for room in rooms {
         if args.out == "-" {
             eprintln!("Exporting &{room} as {} to stdout", args.format.name());
             let vault = vault.room(room);
             let mut stdout = BufWriter::new(io::stdout());
-            export_room(&vault, &mut stdout, args.format).await?;
+            export_room(&vault, &mut stdout, args.format, timestamps, &filter).await?;
             stdout.flush()?;
         } else {
             let out = format_out(&args.out, &room, args.format);
             eprintln!("Exporting &{room} as {} to {out}", args.format.name());
             let vault = vault.room(room);
+            if args.incremental {
+                export_room_incremental(
+                    &vault,
+                    &out,
+                    args.format,
+                    timestamps,
+                    args.rescan,
+                    &filter,
+                )
+                .await?;
+                continue;
+            }
             let mut file = BufWriter::new(File::create(out)?);
-            export_room(&vault, &mut file, args.format).await?;
+            export_room(&vault, &mut file, args.format, timestamps, &filter).await?;
             file.flush()?;
         }
     }
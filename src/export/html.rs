@@ -0,0 +1,188 @@
+use std::io::Write;
+
+use euphoxide::api::MessageId;
+use linkify::{LinkFinder, LinkKind};
+use time::format_description::FormatItem;
+use time::macros::format_description;
+
+use crate::config::Timestamps;
+use crate::euph::{self, SmallMessage};
+use crate::store::Tree;
+use crate::vault::{EuphRoomVault, ExportFilter};
+
+const TIME_FORMAT: &[FormatItem<'_>] =
+    format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
+
+const STYLE: &str = "
+body { font-family: sans-serif; }
+details > summary { list-style-position: outside; }
+.children { margin-left: 1.5em; padding-left: 0.5em; border-left: 1px solid #ccc; }
+.msg { white-space: pre-wrap; }
+.time, .time a { color: #888; font-family: monospace; text-decoration: none; }
+.nick { font-weight: bold; }
+.me { color: #888; font-style: italic; }
+.deleted, .placeholder { color: #888; font-style: italic; }
+:target { background-color: #ffc; }
+";
+
+pub async fn export<W: Write>(
+    vault: &EuphRoomVault,
+    out: &mut W,
+    timestamps: &Timestamps,
+    filter: &ExportFilter,
+) -> anyhow::Result<()> {
+    let room = escape(vault.room());
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>")?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>&amp;{room}</title>")?;
+    writeln!(out, "<style>{STYLE}</style>")?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "<h1>&amp;{room}</h1>")?;
+
+    let mut exported_trees = 0;
+    let mut exported_msgs = 0;
+    for root_id in vault.export_root_ids(filter.clone()).await? {
+        let tree = vault.export_tree(root_id, filter.clone()).await?;
+        if tree.len() == 0 {
+            // No message of the tree matches the filter
+            continue;
+        }
+        write_tree(out, timestamps, &tree, root_id)?;
+
+        exported_trees += 1;
+        exported_msgs += tree.len();
+
+        if exported_trees % 10000 == 0 {
+            eprintln!("  {exported_trees} trees, {exported_msgs} messages")
+        }
+    }
+    eprintln!("  {exported_trees} trees, {exported_msgs} messages in total");
+
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")?;
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            _ => result.push(char),
+        }
+    }
+    result
+}
+
+/// Escape a message's content, turning urls into links and replacing emoji
+/// like the chat does.
+fn content_html(content: &str) -> String {
+    let mut finder = LinkFinder::new();
+    finder.url_must_have_scheme(false).kinds(&[LinkKind::Url]);
+
+    let mut result = String::new();
+    for span in finder.spans(content) {
+        let text = span.as_str();
+        if span.kind().is_some() {
+            // Same as when opening links from the links popup
+            let href = if text.starts_with("http://") || text.starts_with("https://") {
+                text.to_string()
+            } else {
+                format!("https://{text}")
+            };
+            result.push_str(&format!(
+                "<a href=\"{}\">{}</a>",
+                escape(&href),
+                escape(text)
+            ));
+        } else {
+            result.push_str(&escape(&euph::EMOJI.replace(text)));
+        }
+    }
+    result
+}
+
+fn write_tree<W: Write>(
+    out: &mut W,
+    timestamps: &Timestamps,
+    tree: &Tree<SmallMessage>,
+    id: MessageId,
+) -> anyhow::Result<()> {
+    let children = tree.children(&id).filter(|c| !c.is_empty());
+
+    if children.is_some() {
+        write!(out, "<details open><summary>")?;
+    } else {
+        write!(out, "<div>")?;
+    }
+
+    if let Some(msg) = tree.msg(&id) {
+        write_msg(out, timestamps, msg)?;
+    } else {
+        write!(out, "<span class=\"placeholder\">[...]</span>")?;
+    }
+
+    if let Some(children) = children {
+        writeln!(out, "</summary>")?;
+        writeln!(out, "<div class=\"children\">")?;
+        for child in children {
+            write_tree(out, timestamps, tree, *child)?;
+        }
+        writeln!(out, "</div>")?;
+        writeln!(out, "</details>")?;
+    } else {
+        writeln!(out, "</div>")?;
+    }
+
+    Ok(())
+}
+
+fn write_msg<W: Write>(
+    out: &mut W,
+    timestamps: &Timestamps,
+    msg: &SmallMessage,
+) -> anyhow::Result<()> {
+    let id = msg.id.0;
+    let time = timestamps.format(msg.time.0, TIME_FORMAT);
+    let (r, g, b) = euph::nick_color(&msg.nick);
+    let nick = escape(&euph::EMOJI.replace(&msg.nick));
+    let me = msg.content.strip_prefix("/me").filter(|_| !msg.deleted);
+
+    write!(out, "<span class=\"msg\" id=\"{id}\">")?;
+    write!(
+        out,
+        "<span class=\"time\"><a href=\"#{id}\">{time}</a></span> "
+    )?;
+    if me.is_some() {
+        write!(out, "<span class=\"me\">*</span>")?;
+        write!(
+            out,
+            "<span class=\"nick\" style=\"color: rgb({r}, {g}, {b})\">{nick}</span> "
+        )?;
+    } else {
+        write!(
+            out,
+            "[<span class=\"nick\" style=\"color: rgb({r}, {g}, {b})\">{nick}</span>] "
+        )?;
+    }
+
+    if msg.deleted {
+        write!(out, "<span class=\"deleted\">[deleted]</span>")?;
+    } else if let Some(content) = me {
+        let content = content_html(content.trim());
+        write!(out, "<span class=\"me\">{content}*</span>")?;
+    } else {
+        let content = content_html(msg.content.trim());
+        write!(out, "<span class=\"content\">{content}</span>")?;
+    }
+
+    write!(out, "</span>")?;
+    Ok(())
+}
@@ -1,16 +1,23 @@
-use std::io::Write;
+use std::fs::File;
+use std::io::{BufWriter, Seek, Write};
 
-use crate::vault::EuphRoomVault;
+use crate::vault::{EuphRoomVault, ExportFilter};
 
 const CHUNK_SIZE: usize = 10000;
 
-pub async fn export<W: Write>(vault: &EuphRoomVault, file: &mut W) -> anyhow::Result<()> {
+pub async fn export<W: Write>(
+    vault: &EuphRoomVault,
+    file: &mut W,
+    filter: &ExportFilter,
+) -> anyhow::Result<()> {
     write!(file, "[")?;
 
     let mut total = 0;
     let mut offset = 0;
     loop {
-        let messages = vault.chunk_at_offset(CHUNK_SIZE, offset).await?;
+        let messages = vault
+            .chunk_at_offset(filter.clone(), CHUNK_SIZE, offset)
+            .await?;
         offset += messages.len();
 
         if messages.is_empty() {
@@ -38,11 +45,17 @@ pub async fn export<W: Write>(vault: &EuphRoomVault, file: &mut W) -> anyhow::Re
     Ok(())
 }
 
-pub async fn export_stream<W: Write>(vault: &EuphRoomVault, file: &mut W) -> anyhow::Result<()> {
+pub async fn export_stream<W: Write>(
+    vault: &EuphRoomVault,
+    file: &mut W,
+    filter: &ExportFilter,
+) -> anyhow::Result<()> {
     let mut total = 0;
     let mut offset = 0;
     loop {
-        let messages = vault.chunk_at_offset(CHUNK_SIZE, offset).await?;
+        let messages = vault
+            .chunk_at_offset(filter.clone(), CHUNK_SIZE, offset)
+            .await?;
         offset += messages.len();
 
         if messages.is_empty() {
@@ -63,3 +76,40 @@ pub async fn export_stream<W: Write>(vault: &EuphRoomVault, file: &mut W) -> any
     eprintln!("  {total} messages in total");
     Ok(())
 }
+
+/// Append all messages newer than [`ExportFilter::after`], recording the last
+/// exported message and the length of the file in the vault after every chunk.
+pub async fn export_stream_incremental(
+    vault: &EuphRoomVault,
+    file: &mut BufWriter<File>,
+    out: String,
+    mut filter: ExportFilter,
+) -> anyhow::Result<()> {
+    let mut total = 0;
+    loop {
+        let messages = vault.chunk_at_offset(filter.clone(), CHUNK_SIZE, 0).await?;
+        let Some(last_id) = messages.last().map(|msg| msg.id) else {
+            break;
+        };
+
+        for message in messages {
+            serde_json::to_writer(&mut *file, &message)?; // Fancy reborrow! :D
+            writeln!(file)?;
+            total += 1;
+        }
+
+        file.flush()?;
+        let len = file.get_mut().stream_position()?;
+        vault
+            .set_export_checkpoint(out.clone(), last_id, len)
+            .await?;
+        filter.after = Some(last_id);
+
+        if total % 100000 == 0 {
+            eprintln!("  {total} messages");
+        }
+    }
+
+    eprintln!("  {total} messages in total");
+    Ok(())
+}
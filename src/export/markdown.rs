@@ -0,0 +1,147 @@
+use std::io::Write;
+
+use time::format_description::FormatItem;
+use time::macros::format_description;
+
+use crate::config::Timestamps;
+use crate::euph::SmallMessage;
+use crate::vault::{EuphRoomVault, ExportFilter};
+
+use super::text::write_tree;
+
+const TIME_FORMAT: &[FormatItem<'_>] = format_description!("[hour]:[minute]:[second]");
+const DATE_FORMAT: &[FormatItem<'_>] = format_description!("[year]-[month]-[day]");
+
+pub async fn export<W: Write>(
+    vault: &EuphRoomVault,
+    out: &mut W,
+    timestamps: &Timestamps,
+    filter: &ExportFilter,
+) -> anyhow::Result<()> {
+    // The table of contents comes before the trees, so the day of each tree
+    // is determined up front.
+    let root_ids = vault.export_root_ids(filter.clone()).await?;
+    let days = vault
+        .export_tree_times(root_ids.clone())
+        .await?
+        .into_iter()
+        .map(|time| time.map(|time| timestamps.time_zone.convert(time.0).date()))
+        .collect::<Vec<_>>();
+
+    writeln!(out, "# &{}", vault.room())?;
+    writeln!(out)?;
+    let mut last_day = None;
+    for day in days.iter().flatten() {
+        if last_day != Some(day) {
+            // Anchors are generated from the headings, like on GitHub.
+            let day = day.format(DATE_FORMAT)?;
+            writeln!(out, "- [{day}](#{day})")?;
+        }
+        last_day = Some(day);
+    }
+
+    let mut last_day = None;
+    let mut exported_trees = 0;
+    let mut exported_msgs = 0;
+    for (root_id, day) in root_ids.into_iter().zip(days) {
+        let tree = vault.export_tree(root_id, filter.clone()).await?;
+        if tree.len() == 0 {
+            // No message of the tree matches the filter
+            continue;
+        }
+
+        if let Some(day) = day {
+            if last_day != Some(day) {
+                writeln!(out)?;
+                writeln!(out, "## {}", day.format(DATE_FORMAT)?)?;
+                writeln!(out)?;
+                last_day = Some(day);
+            }
+        }
+
+        write_tree(out, &tree, root_id, 0, &mut |out, msg, indent| {
+            write_msg(out, timestamps, msg, indent)
+        })?;
+
+        exported_trees += 1;
+        exported_msgs += tree.len();
+
+        if exported_trees % 10000 == 0 {
+            eprintln!("  {exported_trees} trees, {exported_msgs} messages")
+        }
+    }
+    eprintln!("  {exported_trees} trees, {exported_msgs} messages in total");
+
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for char in text.chars() {
+        if matches!(char, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '~') {
+            result.push('\\');
+        }
+        result.push(char);
+    }
+    result
+}
+
+/// A code fence that is longer than any sequence of backticks in the content.
+fn fence(content: &str) -> String {
+    let longest = content
+        .split(|c| c != '`')
+        .map(|backticks| backticks.len())
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+fn write_msg<W: Write>(
+    out: &mut W,
+    timestamps: &Timestamps,
+    msg: Option<&SmallMessage>,
+    indent: usize,
+) -> anyhow::Result<()> {
+    let indent_string = "  ".repeat(indent);
+
+    let Some(msg) = msg else {
+        writeln!(out, "{indent_string}- \\[...\\]")?;
+        return Ok(());
+    };
+
+    let time = timestamps.format(msg.time.0, TIME_FORMAT);
+    let nick = escape(&msg.nick);
+    let content = msg.content.trim();
+
+    if msg.deleted {
+        writeln!(out, "{indent_string}- {time} **{nick}**: *\\[deleted\\]*")?;
+    } else if content.contains('\n') {
+        // Content that already contains code blocks is kept as it is so the
+        // code blocks are preserved. Anything else is put into a code block so
+        // its line breaks and indentation survive.
+        let has_fences = content.lines().any(|l| l.trim_start().starts_with("```"));
+        writeln!(out, "{indent_string}- {time} **{nick}**:")?;
+        if has_fences {
+            writeln!(out)?;
+            for line in content.lines() {
+                writeln!(out, "{indent_string}  {line}")?;
+            }
+            writeln!(out)?;
+        } else {
+            let fence = fence(content);
+            writeln!(out, "{indent_string}  {fence}")?;
+            for line in content.lines() {
+                writeln!(out, "{indent_string}  {line}")?;
+            }
+            writeln!(out, "{indent_string}  {fence}")?;
+        }
+    } else {
+        writeln!(
+            out,
+            "{indent_string}- {time} **{nick}**: {}",
+            escape(content)
+        )?;
+    }
+
+    Ok(())
+}
@@ -5,22 +5,40 @@ use time::format_description::FormatItem;
 use time::macros::format_description;
 use unicode_width::UnicodeWidthStr;
 
+use crate::config::Timestamps;
 use crate::euph::SmallMessage;
 use crate::store::Tree;
-use crate::vault::EuphRoomVault;
+use crate::vault::{EuphRoomVault, ExportFilter};
 
 const TIME_FORMAT: &[FormatItem<'_>] =
     format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
-const TIME_EMPTY: &str = "                   ";
 
-pub async fn export<W: Write>(vault: &EuphRoomVault, out: &mut W) -> anyhow::Result<()> {
+pub async fn export<W: Write>(
+    vault: &EuphRoomVault,
+    out: &mut W,
+    timestamps: &Timestamps,
+    filter: &ExportFilter,
+) -> anyhow::Result<()> {
+    let time_empty = " ".repeat(timestamps.width(TIME_FORMAT));
     let mut exported_trees = 0;
     let mut exported_msgs = 0;
-    let mut root_id = vault.first_root_id().await?;
-    while let Some(some_root_id) = root_id {
-        let tree = vault.tree(some_root_id).await?;
-        write_tree(out, &tree, some_root_id, 0)?;
-        root_id = vault.next_root_id(some_root_id).await?;
+    for root_id in vault.export_root_ids(filter.clone()).await? {
+        let tree = vault.export_tree(root_id, filter.clone()).await?;
+        if tree.len() == 0 {
+            // No message of the tree matches the filter
+            continue;
+        }
+        write_tree(out, &tree, root_id, 0, &mut |out, msg, indent| {
+            let indent_string = "| ".repeat(indent);
+            match msg {
+                // Already exported by a previous incremental export
+                Some(msg) if filter.after >= Some(msg.id) => {
+                    write_placeholder(out, &time_empty, &indent_string)
+                }
+                Some(msg) => write_msg(out, timestamps, &time_empty, &indent_string, msg),
+                None => write_placeholder(out, &time_empty, &indent_string),
+            }
+        })?;
 
         exported_trees += 1;
         exported_msgs += tree.len();
@@ -34,23 +52,26 @@ pub async fn export<W: Write>(vault: &EuphRoomVault, out: &mut W) -> anyhow::Res
     Ok(())
 }
 
-fn write_tree<W: Write>(
+/// Write a tree depth-first, one message at a time.
+///
+/// The messages are passed to `write_msg` together with their depth in the
+/// tree. Messages missing from the tree are passed as `None`.
+pub(super) fn write_tree<W, F>(
     out: &mut W,
     tree: &Tree<SmallMessage>,
     id: MessageId,
     indent: usize,
-) -> anyhow::Result<()> {
-    let indent_string = "| ".repeat(indent);
-
-    if let Some(msg) = tree.msg(&id) {
-        write_msg(out, &indent_string, msg)?;
-    } else {
-        write_placeholder(out, &indent_string)?;
-    }
+    write_msg: &mut F,
+) -> anyhow::Result<()>
+where
+    W: Write,
+    F: FnMut(&mut W, Option<&SmallMessage>, usize) -> anyhow::Result<()>,
+{
+    write_msg(out, tree.msg(&id), indent)?;
 
     if let Some(children) = tree.children(&id) {
         for child in children {
-            write_tree(out, tree, *child, indent + 1)?;
+            write_tree(out, tree, *child, indent + 1, write_msg)?;
         }
     }
 
@@ -59,29 +80,36 @@ fn write_tree<W: Write>(
 
 fn write_msg<W: Write>(
     file: &mut W,
+    timestamps: &Timestamps,
+    time_empty: &str,
     indent_string: &str,
     msg: &SmallMessage,
 ) -> anyhow::Result<()> {
     let nick = &msg.nick;
     let nick_empty = " ".repeat(nick.width());
+    let content = if msg.deleted {
+        "[deleted]"
+    } else {
+        &msg.content
+    };
 
-    for (i, line) in msg.content.lines().enumerate() {
+    for (i, line) in content.lines().enumerate() {
         if i == 0 {
-            let time = msg
-                .time
-                .0
-                .format(TIME_FORMAT)
-                .expect("time can be formatted");
+            let time = timestamps.format(msg.time.0, TIME_FORMAT);
             writeln!(file, "{time} {indent_string}[{nick}] {line}")?;
         } else {
-            writeln!(file, "{TIME_EMPTY} {indent_string}| {nick_empty} {line}")?;
+            writeln!(file, "{time_empty} {indent_string}| {nick_empty} {line}")?;
         }
     }
 
     Ok(())
 }
 
-fn write_placeholder<W: Write>(file: &mut W, indent_string: &str) -> anyhow::Result<()> {
-    writeln!(file, "{TIME_EMPTY} {indent_string}[...]")?;
+fn write_placeholder<W: Write>(
+    file: &mut W,
+    time_empty: &str,
+    indent_string: &str,
+) -> anyhow::Result<()> {
+    writeln!(file, "{time_empty} {indent_string}[...]")?;
     Ok(())
 }
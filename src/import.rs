@@ -0,0 +1,139 @@
+//! Import room logs exported as json from the command line.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
+
+use euphoxide::api::{Message, MessageId};
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use tokio::sync::mpsc;
+use tokio::task;
+
+use crate::vault::EuphVault;
+
+const CHUNK_SIZE: usize = 10000;
+
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// File containing the messages, as written by the `json` or `json-stream`
+    /// export formats.
+    ///
+    /// If the value is a literal `-`, the messages are read from stdin.
+    file: PathBuf,
+
+    /// Room to import the messages into.
+    #[arg(long, short)]
+    room: String,
+
+    /// Assert that the file contains every message sent between its oldest
+    /// and its newest message.
+    ///
+    /// Otherwise, cove won't know whether messages are missing in between and
+    /// will try to download them again.
+    #[arg(long)]
+    continuous: bool,
+}
+
+fn open_input(args: &Args) -> io::Result<Box<dyn BufRead + Send>> {
+    Ok(if args.file.as_os_str() == "-" {
+        Box::new(BufReader::new(io::stdin()))
+    } else {
+        Box::new(BufReader::new(File::open(&args.file)?))
+    })
+}
+
+/// Skip leading whitespace and return the first byte of the input, if any.
+fn peek_first_byte(input: &mut impl BufRead) -> io::Result<Option<u8>> {
+    loop {
+        let buf = input.fill_buf()?;
+        let Some(&first) = buf.first() else {
+            return Ok(None);
+        };
+        if first.is_ascii_whitespace() {
+            input.consume(1);
+        } else {
+            return Ok(Some(first));
+        }
+    }
+}
+
+/// Visits the elements of a json array one by one instead of collecting them.
+struct MsgsVisitor<'a> {
+    tx: &'a mpsc::Sender<Message>,
+}
+
+impl<'de> Visitor<'de> for MsgsVisitor<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("an array of messages")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(msg) = seq.next_element::<Message>()? {
+            if self.tx.blocking_send(msg).is_err() {
+                return Err(de::Error::custom("import was aborted"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse the messages and send them one by one without reading the whole
+/// input into memory first.
+fn read_msgs(mut input: impl BufRead, tx: mpsc::Sender<Message>) -> anyhow::Result<()> {
+    // The json format is an array of messages while the json stream format
+    // consists of one message after another.
+    if peek_first_byte(&mut input)? == Some(b'[') {
+        let mut de = serde_json::Deserializer::from_reader(input);
+        de.deserialize_seq(MsgsVisitor { tx: &tx })?;
+        de.end()?;
+    } else {
+        for msg in serde_json::Deserializer::from_reader(input).into_iter() {
+            if tx.blocking_send(msg?).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub async fn import(vault: &EuphVault, args: Args) -> anyhow::Result<()> {
+    let input = open_input(&args)?;
+    let (tx, mut rx) = mpsc::channel(CHUNK_SIZE);
+    let reader = task::spawn_blocking(move || read_msgs(input, tx));
+
+    eprintln!("Importing messages into &{}", args.room);
+    let room_vault = vault.room(args.room.clone());
+
+    let mut total = 0;
+    let mut span: Option<(MessageId, MessageId)> = None;
+    let mut chunk = vec![];
+    while let Some(msg) = rx.recv().await {
+        span = match span {
+            Some((first, last)) => Some((first.min(msg.id), last.max(msg.id))),
+            None => Some((msg.id, msg.id)),
+        };
+        chunk.push(msg);
+
+        if chunk.len() >= CHUNK_SIZE {
+            total += chunk.len();
+            room_vault.import_msgs(chunk).await?;
+            chunk = vec![];
+            eprintln!("  {total} messages");
+        }
+    }
+    if !chunk.is_empty() {
+        total += chunk.len();
+        room_vault.import_msgs(chunk).await?;
+    }
+    reader.await??;
+
+    if let (true, Some((first, last))) = (args.continuous, span) {
+        room_vault.add_span(first, last).await?;
+    }
+
+    eprintln!("  {total} messages in total");
+    Ok(())
+}
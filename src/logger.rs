@@ -46,7 +46,11 @@ impl ChatMsg for LogMsg {
         self.time
     }
 
-    fn styled(&self) -> (Styled, Styled) {
+    fn mentions(&self, _nick: &str) -> bool {
+        false
+    }
+
+    fn styled(&self, _own_nick: &str) -> (Styled, Styled) {
         let nick_style = match self.level {
             Level::Error => ContentStyle::default().bold().red(),
             Level::Warn => ContentStyle::default().bold().yellow(),
@@ -62,10 +66,6 @@ impl ChatMsg for LogMsg {
     fn edit(_nick: &str, _content: &str) -> (Styled, Styled) {
         panic!("log is not editable")
     }
-
-    fn pseudo(_nick: &str, _content: &str) -> (Styled, Styled) {
-        panic!("log is not editable")
-    }
 }
 
 /// Prints all error messages when dropped.
@@ -171,10 +171,38 @@ impl MsgStore<LogMsg> for Logger {
         Ok(None)
     }
 
+    async fn older_mention_msg_id(
+        &self,
+        _id: &usize,
+        _nick: &str,
+    ) -> Result<Option<usize>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn newer_mention_msg_id(
+        &self,
+        _id: &usize,
+        _nick: &str,
+    ) -> Result<Option<usize>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn gaps(&self) -> Result<Vec<(usize, usize)>, Self::Error> {
+        Ok(vec![])
+    }
+
     async fn unseen_msgs_count(&self) -> Result<usize, Self::Error> {
         Ok(0)
     }
 
+    async fn draft(&self, _parent: Option<&usize>) -> Result<Option<String>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn set_draft(&self, _parent: Option<&usize>, _content: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
     async fn set_seen(&self, _id: &usize, _seen: bool) -> Result<(), Self::Error> {
         Ok(())
     }
@@ -11,15 +11,18 @@
 
 // TODO Enable warn(unreachable_pub)?
 // TODO Remove unnecessary Debug impls and compare compile times
-// TODO Time zones other than UTC
 // TODO Fix password room auth
 
 mod config;
+mod duration;
 mod euph;
 mod export;
+mod import;
 mod logger;
 mod macros;
+mod search;
 mod store;
+mod sync;
 mod ui;
 mod vault;
 
@@ -43,6 +46,12 @@ enum Command {
     Run,
     /// Export room logs as plain text files.
     Export(export::Args),
+    /// Import room logs exported as json.
+    Import(import::Args),
+    /// Search for messages in the vault.
+    Search(search::Args),
+    /// Download the full history of a room.
+    Sync(sync::Args),
     /// Compact and clean up vault.
     Gc,
     /// Clear euphoria session cookies.
@@ -146,7 +155,10 @@ async fn main() -> anyhow::Result<()> {
 
     match args.command.unwrap_or_default() {
         Command::Run => run(logger, logger_rx, config, &vault, args.measure_widths).await?,
-        Command::Export(args) => export::export(&vault.euph(), args).await?,
+        Command::Export(args) => export::export(&vault.euph(), args, &config.timestamps).await?,
+        Command::Import(args) => import::import(&vault.euph(), args).await?,
+        Command::Search(args) => search::search(&vault.euph(), args, &config.timestamps).await?,
+        Command::Sync(args) => sync::sync(&vault.euph(), args, config).await?,
         Command::Gc => {
             eprintln!("Cleaning up and compacting vault");
             eprintln!("This may take a while...");
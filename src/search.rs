@@ -0,0 +1,157 @@
+//! Search messages in the vault from the command line.
+
+use std::io::{self, BufWriter, Write};
+
+use euphoxide::api::{Message, Time};
+use serde::Serialize;
+use time::format_description::FormatItem;
+use time::macros::format_description;
+use time::OffsetDateTime;
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::Timestamps;
+use crate::export::parse_time;
+use crate::vault::EuphVault;
+
+const TIME_FORMAT: &[FormatItem<'_>] =
+    format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum Format {
+    /// One line per message with room, time, id and nick.
+    Text,
+    /// Array of objects containing the room and the message in the same format
+    /// as the euphoria API uses.
+    Json,
+    /// Objects containing the room and the message in the same format as the
+    /// euphoria API uses, one per line.
+    JsonStream,
+}
+
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// Words that must appear in a message's content.
+    ///
+    /// The last word is also matched as a prefix.
+    query: Option<String>,
+
+    /// Rooms to search in.
+    ///
+    /// If no rooms are specified, all rooms are searched.
+    #[arg(long, short)]
+    room: Vec<String>,
+
+    /// Only show messages sent by this nick (case insensitive).
+    #[arg(long, short)]
+    nick: Option<String>,
+
+    /// Only show messages sent at or after this time.
+    ///
+    /// Either an RFC 3339 timestamp or a time relative to now like `12h`, `7d`
+    /// or `2w`.
+    #[arg(long, value_parser = parse_time)]
+    since: Option<OffsetDateTime>,
+
+    /// Only show messages sent at or before this time.
+    ///
+    /// Either an RFC 3339 timestamp or a time relative to now like `12h`, `7d`
+    /// or `2w`.
+    #[arg(long, value_parser = parse_time)]
+    until: Option<OffsetDateTime>,
+
+    /// Show at most this many of the newest matching messages.
+    #[arg(long, short)]
+    limit: Option<usize>,
+
+    /// Format of the output.
+    #[arg(long, short, value_enum, default_value_t = Format::Text)]
+    format: Format,
+}
+
+#[derive(Serialize)]
+struct SearchResult {
+    room: String,
+    message: Message,
+}
+
+pub async fn search(vault: &EuphVault, args: Args, timestamps: &Timestamps) -> anyhow::Result<()> {
+    let mut rooms = if args.room.is_empty() {
+        vault.rooms().await?
+    } else {
+        args.room.clone()
+    };
+    rooms.sort_unstable();
+    rooms.dedup();
+
+    let mut results = vec![];
+    for room in rooms {
+        let msgs = vault
+            .room(room.clone())
+            .search_full(
+                args.query.clone(),
+                args.nick.clone(),
+                args.since.map(Time),
+                args.until.map(Time),
+                args.limit,
+            )
+            .await?;
+        results.extend(msgs.into_iter().map(|message| SearchResult {
+            room: room.clone(),
+            message,
+        }));
+    }
+
+    // Each room's results are already limited, but the limit should apply to
+    // the combined results of all rooms.
+    results.sort_by_key(|r| (r.message.time, r.message.id));
+    if let Some(limit) = args.limit {
+        let excess = results.len().saturating_sub(limit);
+        results.drain(..excess);
+    }
+
+    let mut out = BufWriter::new(io::stdout());
+    match args.format {
+        Format::Text => write_text(&mut out, &results, timestamps)?,
+        Format::Json => write_json(&mut out, &results)?,
+        Format::JsonStream => write_json_stream(&mut out, &results)?,
+    }
+    out.flush()?;
+
+    eprintln!("{} messages found", results.len());
+    Ok(())
+}
+
+fn write_text<W: Write>(
+    out: &mut W,
+    results: &[SearchResult],
+    timestamps: &Timestamps,
+) -> anyhow::Result<()> {
+    for SearchResult { room, message } in results {
+        let time = timestamps.format(message.time.0, TIME_FORMAT);
+        let id = message.id.0;
+        let nick = &message.sender.name;
+        let prefix = format!("&{room} {time} {id} [{nick}]");
+        let prefix_empty = " ".repeat(prefix.width());
+
+        let mut lines = message.content.lines();
+        writeln!(out, "{prefix} {}", lines.next().unwrap_or_default())?;
+        for line in lines {
+            writeln!(out, "{prefix_empty} {line}")?;
+        }
+    }
+    Ok(())
+}
+
+fn write_json<W: Write>(out: &mut W, results: &[SearchResult]) -> anyhow::Result<()> {
+    serde_json::to_writer(&mut *out, results)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_json_stream<W: Write>(out: &mut W, results: &[SearchResult]) -> anyhow::Result<()> {
+    for result in results {
+        serde_json::to_writer(&mut *out, result)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
@@ -86,6 +86,10 @@ impl<M: Msg> Tree<M> {
         self.msgs.get(id)
     }
 
+    pub fn msgs(&self) -> impl Iterator<Item = &M> {
+        self.msgs.values()
+    }
+
     pub fn parent(&self, id: &M::Id) -> Option<M::Id> {
         self.msg(id).and_then(|m| m.parent())
     }
@@ -128,6 +132,21 @@ impl<M: Msg> Tree<M> {
             .find(|(s, _)| *s == id)
             .map(|(_, s)| s.clone())
     }
+
+    /// The messages of the subtree starting at `id`, in no particular order.
+    pub fn into_subtree_msgs(mut self, id: &M::Id) -> Vec<M> {
+        let mut msgs = vec![];
+        let mut todo = vec![id.clone()];
+        while let Some(id) = todo.pop() {
+            if let Some(children) = self.children.get(&id) {
+                todo.extend(children.iter().cloned());
+            }
+            if let Some(msg) = self.msgs.remove(&id) {
+                msgs.push(msg);
+            }
+        }
+        msgs
+    }
 }
 
 #[async_trait]
@@ -148,7 +167,26 @@ pub trait MsgStore<M: Msg> {
     async fn newest_unseen_msg_id(&self) -> Result<Option<M::Id>, Self::Error>;
     async fn older_unseen_msg_id(&self, id: &M::Id) -> Result<Option<M::Id>, Self::Error>;
     async fn newer_unseen_msg_id(&self, id: &M::Id) -> Result<Option<M::Id>, Self::Error>;
+    async fn older_mention_msg_id(
+        &self,
+        id: &M::Id,
+        nick: &str,
+    ) -> Result<Option<M::Id>, Self::Error>;
+    async fn newer_mention_msg_id(
+        &self,
+        id: &M::Id,
+        nick: &str,
+    ) -> Result<Option<M::Id>, Self::Error>;
+    /// Ranges of messages known to be missing from the store.
+    ///
+    /// Each gap is given as the ids of the known messages directly before and
+    /// after it.
+    async fn gaps(&self) -> Result<Vec<(M::Id, M::Id)>, Self::Error>;
     async fn unseen_msgs_count(&self) -> Result<usize, Self::Error>;
+    /// The unsent message last composed as a reply to `parent`.
+    async fn draft(&self, parent: Option<&M::Id>) -> Result<Option<String>, Self::Error>;
+    /// Setting an empty draft removes it.
+    async fn set_draft(&self, parent: Option<&M::Id>, content: &str) -> Result<(), Self::Error>;
     async fn set_seen(&self, id: &M::Id, seen: bool) -> Result<(), Self::Error>;
     async fn set_older_seen(&self, id: &M::Id, seen: bool) -> Result<(), Self::Error>;
 }
@@ -0,0 +1,82 @@
+//! Download the full history of a room from the command line.
+
+use std::sync::{Arc, Mutex};
+
+use euphoxide::bot::instance::ServerConfig;
+use tokio::sync::mpsc;
+
+use crate::config::{Config, Notify};
+use crate::euph;
+use crate::vault::EuphVault;
+
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// Room whose history to download.
+    room: String,
+
+    /// Redownload all known messages to pick up edits and deletions instead.
+    #[arg(long)]
+    verify: bool,
+}
+
+pub async fn sync(vault: &EuphVault, args: Args, config: &'static Config) -> anyhow::Result<()> {
+    let room_config = config.euph_room(&args.room);
+    let cookies = vault.cookies().await?;
+    let instance_config = ServerConfig::default()
+        .cookies(Arc::new(Mutex::new(cookies)))
+        .room(args.room.clone())
+        .name(format!("{}-sync", args.room))
+        .human(true)
+        .username(room_config.username)
+        .force_username(room_config.force_username)
+        .password(room_config.password);
+
+    let room_vault = vault.room(args.room.clone());
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut room = euph::Room::new(
+        room_vault.clone(),
+        instance_config,
+        Notify::Off,
+        None,
+        move |e| {
+            let _ = tx.send(e);
+        },
+    );
+
+    if !room.start_sync(args.verify).await {
+        anyhow::bail!("history of &{} can't be downloaded", args.room);
+    }
+
+    if args.verify {
+        eprintln!("Verifying history of &{}", args.room);
+    } else {
+        eprintln!("Downloading history of &{}", args.room);
+    }
+    let mut msgs = 0;
+    while let Some(event) = rx.recv().await {
+        room.handle_event(event).await;
+
+        let Some(progress) = room.sync_progress() else {
+            // Downloading may also have stopped because of an error.
+            if let (false, Some((Some(_), _))) = (args.verify, room_vault.last_span().await?) {
+                anyhow::bail!("stopped after downloading {msgs} messages");
+            }
+            eprintln!("Done, downloaded {msgs} messages");
+            return Ok(());
+        };
+
+        if progress.msgs != msgs {
+            msgs = progress.msgs;
+            match progress.oldest {
+                Some(oldest) => eprintln!("{msgs} messages, back to {}", oldest.0.date()),
+                None => eprintln!("{msgs} messages"),
+            }
+        }
+
+        if room.stopped() {
+            break;
+        }
+    }
+
+    anyhow::bail!("connection to &{} stopped", args.room)
+}
@@ -22,8 +22,9 @@ use crate::macros::{logging_unwrap, ok_or_return, some_or_return};
 use crate::vault::Vault;
 
 pub use self::chat::ChatMsg;
-use self::chat::ChatState;
-use self::input::{key, InputEvent, KeyBindingsList};
+use self::chat::{ChatState, DRAFT_SAVE_INTERVAL};
+pub use self::input::InputEvent;
+use self::input::{key, KeyBindingsList};
 use self::rooms::Rooms;
 use self::widgets::layer::Layer;
 use self::widgets::list::ListState;
@@ -37,6 +38,11 @@ pub enum UiEvent {
     LogChanged,
     Term(crossterm::event::Event),
     Euph(euphoxide::bot::instance::Event),
+    /// Connect to a room and show it, e. g. after starting a private
+    /// conversation.
+    JoinRoom(String),
+    /// Save drafts whose saving was delayed while typing.
+    SaveDrafts,
 }
 
 enum EventHandleResult {
@@ -54,6 +60,7 @@ enum Mode {
 // TODO Add Error for anything that can go wrong while rendering
 
 pub struct Ui {
+    config: &'static Config,
     event_tx: UnboundedSender<UiEvent>,
 
     mode: Mode,
@@ -93,15 +100,17 @@ impl Ui {
         // On the other hand, if the crossterm_event_task stops for any reason,
         // the rest of the UI is also shut down and the client stops.
         let mut ui = Self {
+            config,
             event_tx: event_tx.clone(),
             mode: Mode::Main,
             rooms: Rooms::new(config, vault, event_tx.clone()).await,
-            log_chat: ChatState::new(logger),
+            log_chat: ChatState::new(logger, &config.keys, &config.timestamps),
             key_bindings_list: None,
         };
         tokio::select! {
             e = ui.run_main(terminal, event_rx, crossterm_lock) => e?,
             _ = Self::update_on_log_event(logger_rx, &event_tx) => (),
+            _ = Self::save_drafts_regularly(&event_tx) => (),
             e = crossterm_event_task => e??,
         }
         Ok(())
@@ -131,6 +140,13 @@ impl Ui {
         }
     }
 
+    async fn save_drafts_regularly(event_tx: &UnboundedSender<UiEvent>) {
+        loop {
+            tokio::time::sleep(DRAFT_SAVE_INTERVAL).await;
+            ok_or_return!(event_tx.send(UiEvent::SaveDrafts));
+        }
+    }
+
     async fn run_main(
         &mut self,
         terminal: &mut Terminal,
@@ -193,7 +209,7 @@ impl Ui {
         if let Some(key_bindings_list) = &self.key_bindings_list {
             let mut bindings = KeyBindingsList::new(key_bindings_list);
             self.list_key_bindings(&mut bindings).await;
-            Layer::new(vec![widget, bindings.widget()]).into()
+            Layer::new(vec![widget, bindings.widget(&self.config.keys)]).into()
         } else {
             widget
         }
@@ -205,10 +221,18 @@ impl Ui {
         }
     }
 
+    fn toggle_log(&mut self) {
+        self.mode = match self.mode {
+            Mode::Main => Mode::Log,
+            Mode::Log => Mode::Main,
+        };
+    }
+
     async fn list_key_bindings(&self, bindings: &mut KeyBindingsList) {
-        bindings.binding("ctrl+c", "quit cove");
-        bindings.binding("F1, ?", "show this menu");
-        bindings.binding("F12", "toggle log");
+        let keys = &self.config.keys;
+        bindings.action(&keys.general.exit, "quit cove");
+        bindings.action(&keys.general.help, "show this menu");
+        bindings.action(&keys.general.log, "toggle log");
         bindings.empty();
         match self.mode {
             Mode::Main => self.rooms.list_key_bindings(bindings).await,
@@ -238,6 +262,14 @@ impl Ui {
                     EventHandleResult::Continue
                 }
             }
+            UiEvent::JoinRoom(name) => {
+                self.rooms.join_room(name);
+                EventHandleResult::Redraw
+            }
+            UiEvent::SaveDrafts => {
+                self.rooms.save_drafts(false).await;
+                EventHandleResult::Continue
+            }
         }
     }
 
@@ -248,38 +280,41 @@ impl Ui {
         event: crossterm::event::Event,
     ) -> EventHandleResult {
         let event = some_or_return!(InputEvent::from_event(event), EventHandleResult::Continue);
+        let keys = &self.config.keys;
 
-        if let key!(Ctrl + 'c') = event {
+        if keys.general.exit.matches(&event) {
             // Exit unconditionally on ctrl+c. Previously, shift+q would also
             // unconditionally exit, but that interfered with typing text in
             // inline editors.
+            self.rooms.save_drafts(true).await;
             return EventHandleResult::Stop;
         }
 
         // Key bindings list overrides any other bindings if visible
         if let Some(key_bindings_list) = &mut self.key_bindings_list {
-            match event {
-                key!(Esc) | key!(F 1) | key!('?') => self.key_bindings_list = None,
-                key!('k') | key!(Up) => key_bindings_list.scroll_up(1),
-                key!('j') | key!(Down) => key_bindings_list.scroll_down(1),
-                _ => return EventHandleResult::Continue,
+            if matches!(event, key!(Esc)) || keys.general.help.matches(&event) {
+                self.key_bindings_list = None;
+            } else if keys.cursor.up.matches(&event) {
+                key_bindings_list.scroll_up(1);
+            } else if keys.cursor.down.matches(&event) {
+                key_bindings_list.scroll_down(1);
+            } else {
+                return EventHandleResult::Continue;
             }
             return EventHandleResult::Redraw;
         }
 
-        match event {
-            key!(F 1) => {
-                self.key_bindings_list = Some(ListState::new());
+        // Keys that would insert text are only handled after the main UI had a
+        // chance to handle them.
+        if !event.is_text() {
+            if keys.general.help.matches(&event) {
+                self.show_key_bindings();
                 return EventHandleResult::Redraw;
             }
-            key!(F 12) => {
-                self.mode = match self.mode {
-                    Mode::Main => Mode::Log,
-                    Mode::Log => Mode::Main,
-                };
+            if keys.general.log.matches(&event) {
+                self.toggle_log();
                 return EventHandleResult::Redraw;
             }
-            _ => {}
         }
 
         let mut handled = match self.mode {
@@ -301,10 +336,13 @@ impl Ui {
         // Pressing '?' should only open the key bindings list if it doesn't
         // interfere with any part of the main UI, such as entering text in a
         // text editor.
-        if !handled {
-            if let key!('?') = event {
+        if !handled && event.is_text() {
+            if keys.general.help.matches(&event) {
                 self.show_key_bindings();
                 handled = true;
+            } else if keys.general.log.matches(&event) {
+                self.toggle_log();
+                handled = true;
             }
         }
 
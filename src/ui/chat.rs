@@ -1,8 +1,6 @@
-// TODO Implement thread view
-// TODO Implement flat (chronological?) view
-// TODO Implement message search?
-
 mod blocks;
+mod flat;
+mod thread;
 mod tree;
 
 use std::sync::Arc;
@@ -15,11 +13,16 @@ use toss::frame::{Frame, Size};
 use toss::styled::Styled;
 use toss::terminal::Terminal;
 
+use crate::config::{Keys, Timestamps};
 use crate::store::{Msg, MsgStore};
 
+use self::flat::{FlatView, FlatViewState};
+use self::thread::{ThreadView, ThreadViewState};
+pub use self::tree::DRAFT_SAVE_INTERVAL;
 use self::tree::{TreeView, TreeViewState};
 
 use super::input::{InputEvent, KeyBindingsList};
+use super::widgets::editor::EditorState;
 use super::widgets::Widget;
 
 ///////////
@@ -28,47 +31,75 @@ use super::widgets::Widget;
 
 pub trait ChatMsg {
     fn time(&self) -> OffsetDateTime;
-    fn styled(&self) -> (Styled, Styled);
+    /// Whether the message mentions a nick, usually the current session's.
+    fn mentions(&self, nick: &str) -> bool;
+    /// The message's nick and content. Mentions of `own_nick` are highlighted.
+    fn styled(&self, own_nick: &str) -> (Styled, Styled);
     fn edit(nick: &str, content: &str) -> (Styled, Styled);
-    fn pseudo(nick: &str, content: &str) -> (Styled, Styled);
 }
 
 ///////////
 // State //
 ///////////
 
-pub enum Mode {
+pub enum Mode<M, S>
+where
+    M: Msg + Send + Sync,
+    M::Id: Send + Sync,
+    S: MsgStore<M> + Send + Sync,
+{
     Tree,
-    // Thread,
-    // Flat,
+    Thread(ThreadViewState<M, S>),
+    Flat,
 }
 
-pub struct ChatState<M: Msg, S: MsgStore<M>> {
+pub struct ChatState<M, S>
+where
+    M: Msg + Send + Sync,
+    M::Id: Send + Sync,
+    S: MsgStore<M> + Send + Sync,
+{
     store: S,
-    mode: Mode,
+    keys: &'static Keys,
+    timestamps: &'static Timestamps,
+    mode: Mode<M, S>,
     tree: TreeViewState<M, S>,
-    // thread: ThreadView,
-    // flat: FlatView,
+    flat: FlatViewState<M, S>,
 }
 
-impl<M: Msg, S: MsgStore<M> + Clone> ChatState<M, S> {
-    pub fn new(store: S) -> Self {
+impl<M, S> ChatState<M, S>
+where
+    M: Msg + Send + Sync,
+    M::Id: Send + Sync,
+    S: MsgStore<M> + Clone + Send + Sync,
+{
+    pub fn new(store: S, keys: &'static Keys, timestamps: &'static Timestamps) -> Self {
         Self {
             mode: Mode::Tree,
-            tree: TreeViewState::new(store.clone()),
+            tree: TreeViewState::new(store.clone(), keys, timestamps),
+            flat: FlatViewState::new(store.clone(), keys, timestamps),
             store,
+            keys,
+            timestamps,
         }
     }
 }
 
-impl<M: Msg, S: MsgStore<M>> ChatState<M, S> {
+impl<M, S> ChatState<M, S>
+where
+    M: Msg + Send + Sync,
+    M::Id: Send + Sync,
+    S: MsgStore<M> + Clone + Send + Sync,
+{
     pub fn store(&self) -> &S {
         &self.store
     }
 
     pub fn widget(&self, nick: String, focused: bool) -> Chat<M, S> {
-        match self.mode {
+        match &self.mode {
             Mode::Tree => Chat::Tree(self.tree.widget(nick, focused)),
+            Mode::Thread(thread) => Chat::Thread(thread.widget(nick, focused)),
+            Mode::Flat => Chat::Flat(self.flat.widget(nick, focused)),
         }
     }
 }
@@ -89,11 +120,124 @@ impl<M: Msg> Reaction<M> {
     }
 }
 
-impl<M: Msg, S: MsgStore<M>> ChatState<M, S> {
+impl<M, S> ChatState<M, S>
+where
+    M: Msg + Send + Sync,
+    M::Id: Send + Sync,
+    S: MsgStore<M> + Clone + Send + Sync,
+{
     pub async fn list_key_bindings(&self, bindings: &mut KeyBindingsList, can_compose: bool) {
-        match self.mode {
-            Mode::Tree => self.tree.list_key_bindings(bindings, can_compose).await,
+        let keys = self.keys;
+        match &self.mode {
+            Mode::Tree => {
+                self.tree.list_key_bindings(bindings, can_compose).await;
+                if !self.tree.composing().await {
+                    bindings.empty();
+                    bindings.action(
+                        &keys.tree.thread,
+                        "show current message's thread on its own",
+                    );
+                    bindings.action(
+                        &keys.tree.subtree,
+                        "show current message's subtree on its own",
+                    );
+                    bindings.action(&keys.tree.flat, "show messages chronologically");
+                }
+            }
+            Mode::Thread(thread) => {
+                thread.list_key_bindings(bindings, can_compose).await;
+                if !thread.composing().await {
+                    bindings.empty();
+                    bindings.action(&keys.tree.thread, "return to full tree");
+                    bindings.action(
+                        &keys.tree.subtree,
+                        "show current message's subtree on its own",
+                    );
+                    bindings.action(&keys.tree.flat, "show messages chronologically");
+                }
+            }
+            Mode::Flat => {
+                self.flat.list_key_bindings(bindings, can_compose).await;
+                if !self.flat.composing().await {
+                    bindings.empty();
+                    bindings.action(
+                        &keys.tree.thread,
+                        "show current message's thread on its own",
+                    );
+                    bindings.action(
+                        &keys.tree.subtree,
+                        "show current message's subtree on its own",
+                    );
+                    bindings.action(&keys.tree.flat, "show messages as tree");
+                }
+            }
+        }
+    }
+
+    /// Show only the subtree starting at `root`, placing the cursor on
+    /// `cursor`.
+    async fn enter_thread(&mut self, root: M::Id, cursor: M::Id) -> Result<(), S::Error> {
+        self.save_draft(true).await?;
+        let mut thread = ThreadViewState::new(self.store.clone(), root, self.keys, self.timestamps);
+        thread.jump_to(cursor).await?;
+        self.mode = Mode::Thread(thread);
+        Ok(())
+    }
+
+    /// Return to the full tree, moving the tree's cursor to wherever the
+    /// thread view's cursor was.
+    async fn leave_thread(&mut self) -> Result<(), S::Error> {
+        self.save_draft(true).await?;
+        if let Mode::Thread(thread) = &self.mode {
+            if let Some(id) = thread.cursor().await {
+                self.tree.jump_to(id).await?;
+            }
+        }
+        self.mode = Mode::Tree;
+        Ok(())
+    }
+
+    /// Switch between the flat view and the tree view, keeping the cursor on
+    /// the same message.
+    async fn toggle_flat(&mut self, cursor: Option<M::Id>) -> Result<(), S::Error> {
+        self.save_draft(true).await?;
+        if let Mode::Flat = self.mode {
+            if let Some(id) = cursor {
+                self.tree.jump_to(id).await?;
+            }
+            self.mode = Mode::Tree;
+        } else {
+            if let Some(id) = cursor {
+                self.flat.jump_to(id).await?;
+            }
+            self.mode = Mode::Flat;
+        }
+        Ok(())
+    }
+
+    async fn handle_mode_input_event(&mut self, event: &InputEvent) -> Result<bool, S::Error> {
+        let keys = self.keys;
+        let cursor = self.cursor().await;
+        if keys.tree.thread.matches(event) {
+            match &self.mode {
+                Mode::Tree | Mode::Flat => {
+                    if let Some(id) = cursor {
+                        let root = self.store.path(&id).await?.first().clone();
+                        self.enter_thread(root, id).await?;
+                    }
+                }
+                Mode::Thread(_) => self.leave_thread().await?,
+            }
+        } else if keys.tree.subtree.matches(event) {
+            if let Some(id) = cursor {
+                self.enter_thread(id.clone(), id).await?;
+            }
+        } else if keys.tree.flat.matches(event) {
+            self.toggle_flat(cursor).await?;
+        } else {
+            return Ok(false);
         }
+        Ok(true)
     }
 
     pub async fn handle_input_event(
@@ -103,18 +247,59 @@ impl<M: Msg, S: MsgStore<M>> ChatState<M, S> {
         event: &InputEvent,
         can_compose: bool,
     ) -> Result<Reaction<M>, S::Error> {
-        match self.mode {
+        let reaction = match &mut self.mode {
             Mode::Tree => {
                 self.tree
                     .handle_input_event(terminal, crossterm_lock, event, can_compose)
-                    .await
+                    .await?
+            }
+            Mode::Thread(thread) => {
+                thread
+                    .handle_input_event(terminal, crossterm_lock, event, can_compose)
+                    .await?
+            }
+            Mode::Flat => {
+                self.flat
+                    .handle_input_event(terminal, crossterm_lock, event, can_compose)
+                    .await?
+            }
+        };
+
+        if let Reaction::NotHandled = reaction {
+            if self.handle_mode_input_event(event).await? {
+                return Ok(Reaction::Handled);
             }
         }
+
+        Ok(reaction)
     }
 
     pub async fn cursor(&self) -> Option<M::Id> {
-        match self.mode {
+        match &self.mode {
             Mode::Tree => self.tree.cursor().await,
+            Mode::Thread(thread) => thread.cursor().await,
+            Mode::Flat => self.flat.cursor().await,
+        }
+    }
+
+    /// The editor, if a message is currently being composed.
+    pub async fn editor(&self) -> Option<EditorState> {
+        match &self.mode {
+            Mode::Tree => self.tree.editor().await,
+            Mode::Thread(thread) => thread.editor().await,
+            Mode::Flat => self.flat.editor().await,
+        }
+    }
+
+    /// Save the draft currently being composed, if any.
+    ///
+    /// Must be called after modifying the contents of [`Self::editor`]. Unless
+    /// `immediately` is set, saving may be delayed until a later call.
+    pub async fn save_draft(&self, immediately: bool) -> Result<(), S::Error> {
+        match &self.mode {
+            Mode::Tree => self.tree.save_draft(immediately).await,
+            Mode::Thread(thread) => thread.save_draft(immediately).await,
+            Mode::Flat => self.flat.save_draft(immediately).await,
         }
     }
 
@@ -123,9 +308,32 @@ impl<M: Msg, S: MsgStore<M>> ChatState<M, S> {
     ///
     /// If successful, include the message's id as an argument. If unsuccessful,
     /// instead pass a `None`.
-    pub async fn sent(&mut self, id: Option<M::Id>) {
-        match self.mode {
+    pub async fn sent(&mut self, id: Option<M::Id>) -> Result<(), S::Error> {
+        match &mut self.mode {
             Mode::Tree => self.tree.sent(id).await,
+            Mode::Thread(thread) => thread.sent(id).await,
+            Mode::Flat => self.flat.sent(id).await,
+        }
+    }
+
+    /// Move the cursor to a specific message, unfolding its ancestors if
+    /// necessary.
+    ///
+    /// If the message is not part of the currently shown thread, the full tree
+    /// is shown instead.
+    pub async fn jump_to(&mut self, id: M::Id) -> Result<(), S::Error> {
+        self.save_draft(true).await?;
+        if let Mode::Thread(thread) = &self.mode {
+            let path = self.store.path(&id).await?;
+            if !path.into_iter().any(|s| s == *thread.root()) {
+                self.mode = Mode::Tree;
+            }
+        }
+
+        match &mut self.mode {
+            Mode::Tree => self.tree.jump_to(id).await,
+            Mode::Thread(thread) => thread.jump_to(id).await,
+            Mode::Flat => self.flat.jump_to(id).await,
         }
     }
 }
@@ -134,27 +342,38 @@ impl<M: Msg, S: MsgStore<M>> ChatState<M, S> {
 // Widget //
 ////////////
 
-pub enum Chat<M: Msg, S: MsgStore<M>> {
+pub enum Chat<M, S>
+where
+    M: Msg + Send + Sync,
+    M::Id: Send + Sync,
+    S: MsgStore<M> + Send + Sync,
+{
     Tree(TreeView<M, S>),
+    Thread(ThreadView<M, S>),
+    Flat(FlatView<M, S>),
 }
 
 #[async_trait]
 impl<M, S> Widget for Chat<M, S>
 where
-    M: Msg + ChatMsg,
+    M: Msg + ChatMsg + Send + Sync + 'static,
     M::Id: Send + Sync,
-    S: MsgStore<M> + Send + Sync,
+    S: MsgStore<M> + Send + Sync + 'static,
     S::Error: fmt::Display,
 {
     fn size(&self, frame: &mut Frame, max_width: Option<u16>, max_height: Option<u16>) -> Size {
         match self {
             Self::Tree(tree) => tree.size(frame, max_width, max_height),
+            Self::Thread(thread) => thread.size(frame, max_width, max_height),
+            Self::Flat(flat) => flat.size(frame, max_width, max_height),
         }
     }
 
     async fn render(self: Box<Self>, frame: &mut Frame) {
         match *self {
             Self::Tree(tree) => Box::new(tree).render(frame).await,
+            Self::Thread(thread) => Box::new(thread).render(frame).await,
+            Self::Flat(flat) => Box::new(flat).render(frame).await,
         }
     }
 }
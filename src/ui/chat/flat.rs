@@ -0,0 +1,359 @@
+//! Showing all messages in chronological order.
+//!
+//! The flat view reuses the tree view, but presents every message as a tree of
+//! its own. The trees are ordered chronologically and each message that is a
+//! reply mentions its parent.
+
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use crossterm::style::{ContentStyle, Stylize};
+use parking_lot::FairMutex;
+use time::OffsetDateTime;
+use toss::frame::{Frame, Size};
+use toss::styled::Styled;
+use toss::terminal::Terminal;
+
+use crate::config::{Keys, Timestamps};
+use crate::store::{Msg, MsgStore, Path, Tree};
+use crate::ui::input::{InputEvent, KeyBindingsList};
+use crate::ui::widgets::editor::EditorState;
+use crate::ui::widgets::Widget;
+
+use super::tree::{TreeView, TreeViewState};
+use super::{ChatMsg, Reaction};
+
+/// Maximum amount of characters of the parent's content to show in the reply
+/// marker.
+const SNIPPET_LENGTH: usize = 50;
+
+/////////////
+// Message //
+/////////////
+
+/// A message along with its parent, if it has one.
+///
+/// Flat messages never have a parent themselves, so each one forms a tree of
+/// its own.
+pub struct FlatMsg<M> {
+    msg: M,
+    /// `None` if the message has no parent. `Some(None)` if it has a parent
+    /// that is not in the store.
+    parent: Option<Option<M>>,
+}
+
+fn style_reply() -> ContentStyle {
+    ContentStyle::default().dark_grey()
+}
+
+fn snippet(content: &str) -> String {
+    // The marker is only one line high, so newlines would just be confusing.
+    let content = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    if content.chars().count() > SNIPPET_LENGTH {
+        let truncated = content.chars().take(SNIPPET_LENGTH).collect::<String>();
+        format!("{}…", truncated.trim_end())
+    } else {
+        content
+    }
+}
+
+impl<M: ChatMsg> FlatMsg<M> {
+    fn reply_marker(&self, own_nick: &str) -> Option<Styled> {
+        let marker = Styled::new("↳ reply to ", style_reply());
+        Some(match self.parent.as_ref()? {
+            Some(parent) => {
+                let (nick, content) = parent.styled(own_nick);
+                marker
+                    .and_then(nick)
+                    .then(": ", style_reply())
+                    .then(snippet(content.text()), style_reply())
+            }
+            None => marker.then("[...]", style_reply()),
+        })
+    }
+}
+
+impl<M: Msg> Msg for FlatMsg<M> {
+    type Id = M::Id;
+
+    fn id(&self) -> Self::Id {
+        self.msg.id()
+    }
+
+    fn parent(&self) -> Option<Self::Id> {
+        None
+    }
+
+    fn seen(&self) -> bool {
+        self.msg.seen()
+    }
+
+    fn last_possible_id() -> Self::Id {
+        M::last_possible_id()
+    }
+}
+
+impl<M: ChatMsg> ChatMsg for FlatMsg<M> {
+    fn time(&self) -> OffsetDateTime {
+        self.msg.time()
+    }
+
+    fn mentions(&self, nick: &str) -> bool {
+        self.msg.mentions(nick)
+    }
+
+    fn styled(&self, own_nick: &str) -> (Styled, Styled) {
+        let (nick, content) = self.msg.styled(own_nick);
+        match self.reply_marker(own_nick) {
+            Some(marker) => (nick, marker.then_plain("\n").and_then(content)),
+            None => (nick, content),
+        }
+    }
+
+    fn edit(nick: &str, content: &str) -> (Styled, Styled) {
+        M::edit(nick, content)
+    }
+}
+
+///////////
+// Store //
+///////////
+
+/// A [`MsgStore`] presenting each message as its own tree.
+///
+/// Trees are ordered chronologically, so moving between roots is the same as
+/// moving between messages.
+#[derive(Clone)]
+pub struct FlatStore<S> {
+    store: S,
+}
+
+#[async_trait]
+impl<M, S> MsgStore<FlatMsg<M>> for FlatStore<S>
+where
+    M: Msg + Send + Sync,
+    M::Id: Send + Sync,
+    S: MsgStore<M> + Send + Sync,
+{
+    type Error = S::Error;
+
+    async fn path(&self, id: &M::Id) -> Result<Path<M::Id>, Self::Error> {
+        Ok(Path::new(vec![id.clone()]))
+    }
+
+    async fn msg(&self, id: &M::Id) -> Result<Option<FlatMsg<M>>, Self::Error> {
+        let msg = match self.store.msg(id).await? {
+            Some(msg) => msg,
+            None => return Ok(None),
+        };
+        let parent = match msg.parent() {
+            Some(parent_id) => Some(self.store.msg(&parent_id).await?),
+            None => None,
+        };
+        Ok(Some(FlatMsg { msg, parent }))
+    }
+
+    async fn tree(&self, root_id: &M::Id) -> Result<Tree<FlatMsg<M>>, Self::Error> {
+        let msgs = self.msg(root_id).await?.into_iter().collect();
+        Ok(Tree::new(root_id.clone(), msgs))
+    }
+
+    async fn first_root_id(&self) -> Result<Option<M::Id>, Self::Error> {
+        self.store.oldest_msg_id().await
+    }
+
+    async fn last_root_id(&self) -> Result<Option<M::Id>, Self::Error> {
+        self.store.newest_msg_id().await
+    }
+
+    async fn prev_root_id(&self, root_id: &M::Id) -> Result<Option<M::Id>, Self::Error> {
+        self.store.older_msg_id(root_id).await
+    }
+
+    async fn next_root_id(&self, root_id: &M::Id) -> Result<Option<M::Id>, Self::Error> {
+        self.store.newer_msg_id(root_id).await
+    }
+
+    async fn oldest_msg_id(&self) -> Result<Option<M::Id>, Self::Error> {
+        self.store.oldest_msg_id().await
+    }
+
+    async fn newest_msg_id(&self) -> Result<Option<M::Id>, Self::Error> {
+        self.store.newest_msg_id().await
+    }
+
+    async fn older_msg_id(&self, id: &M::Id) -> Result<Option<M::Id>, Self::Error> {
+        self.store.older_msg_id(id).await
+    }
+
+    async fn newer_msg_id(&self, id: &M::Id) -> Result<Option<M::Id>, Self::Error> {
+        self.store.newer_msg_id(id).await
+    }
+
+    async fn oldest_unseen_msg_id(&self) -> Result<Option<M::Id>, Self::Error> {
+        self.store.oldest_unseen_msg_id().await
+    }
+
+    async fn newest_unseen_msg_id(&self) -> Result<Option<M::Id>, Self::Error> {
+        self.store.newest_unseen_msg_id().await
+    }
+
+    async fn older_unseen_msg_id(&self, id: &M::Id) -> Result<Option<M::Id>, Self::Error> {
+        self.store.older_unseen_msg_id(id).await
+    }
+
+    async fn newer_unseen_msg_id(&self, id: &M::Id) -> Result<Option<M::Id>, Self::Error> {
+        self.store.newer_unseen_msg_id(id).await
+    }
+
+    async fn older_mention_msg_id(
+        &self,
+        id: &M::Id,
+        nick: &str,
+    ) -> Result<Option<M::Id>, Self::Error> {
+        self.store.older_mention_msg_id(id, nick).await
+    }
+
+    async fn newer_mention_msg_id(
+        &self,
+        id: &M::Id,
+        nick: &str,
+    ) -> Result<Option<M::Id>, Self::Error> {
+        self.store.newer_mention_msg_id(id, nick).await
+    }
+
+    async fn gaps(&self) -> Result<Vec<(M::Id, M::Id)>, Self::Error> {
+        self.store.gaps().await
+    }
+
+    async fn unseen_msgs_count(&self) -> Result<usize, Self::Error> {
+        self.store.unseen_msgs_count().await
+    }
+
+    async fn draft(&self, parent: Option<&M::Id>) -> Result<Option<String>, Self::Error> {
+        self.store.draft(parent).await
+    }
+
+    async fn set_draft(&self, parent: Option<&M::Id>, content: &str) -> Result<(), Self::Error> {
+        self.store.set_draft(parent, content).await
+    }
+
+    async fn set_seen(&self, id: &M::Id, seen: bool) -> Result<(), Self::Error> {
+        self.store.set_seen(id, seen).await
+    }
+
+    async fn set_older_seen(&self, id: &M::Id, seen: bool) -> Result<(), Self::Error> {
+        self.store.set_older_seen(id, seen).await
+    }
+}
+
+///////////
+// State //
+///////////
+
+pub struct FlatViewState<M, S>
+where
+    M: Msg + Send + Sync,
+    M::Id: Send + Sync,
+    S: MsgStore<M> + Send + Sync,
+{
+    tree: TreeViewState<FlatMsg<M>, FlatStore<S>>,
+}
+
+impl<M, S> FlatViewState<M, S>
+where
+    M: Msg + Send + Sync,
+    M::Id: Send + Sync,
+    S: MsgStore<M> + Clone + Send + Sync,
+{
+    pub fn new(store: S, keys: &'static Keys, timestamps: &'static Timestamps) -> Self {
+        Self {
+            tree: TreeViewState::new(FlatStore { store }, keys, timestamps),
+        }
+    }
+
+    pub fn widget(&self, nick: String, focused: bool) -> FlatView<M, S> {
+        FlatView {
+            tree: self.tree.widget(nick, focused),
+        }
+    }
+
+    pub async fn list_key_bindings(&self, bindings: &mut KeyBindingsList, can_compose: bool) {
+        self.tree.list_key_bindings(bindings, can_compose).await;
+    }
+
+    pub async fn handle_input_event(
+        &mut self,
+        terminal: &mut Terminal,
+        crossterm_lock: &Arc<FairMutex<()>>,
+        event: &InputEvent,
+        can_compose: bool,
+    ) -> Result<Reaction<M>, S::Error> {
+        let reaction = self
+            .tree
+            .handle_input_event(terminal, crossterm_lock, event, can_compose)
+            .await?;
+
+        Ok(match reaction {
+            Reaction::NotHandled => Reaction::NotHandled,
+            Reaction::Handled => Reaction::Handled,
+            Reaction::Composed { parent, content } => Reaction::Composed { parent, content },
+            Reaction::ComposeError(err) => Reaction::ComposeError(err),
+        })
+    }
+
+    pub async fn composing(&self) -> bool {
+        self.tree.composing().await
+    }
+
+    pub async fn editor(&self) -> Option<EditorState> {
+        self.tree.editor().await
+    }
+
+    pub async fn cursor(&self) -> Option<M::Id> {
+        self.tree.cursor().await
+    }
+
+    pub async fn save_draft(&self, immediately: bool) -> Result<(), S::Error> {
+        self.tree.save_draft(immediately).await
+    }
+
+    pub async fn sent(&mut self, id: Option<M::Id>) -> Result<(), S::Error> {
+        self.tree.sent(id).await
+    }
+
+    pub async fn jump_to(&mut self, id: M::Id) -> Result<(), S::Error> {
+        self.tree.jump_to(id).await
+    }
+}
+
+////////////
+// Widget //
+////////////
+
+pub struct FlatView<M, S>
+where
+    M: Msg + Send + Sync,
+    M::Id: Send + Sync,
+    S: MsgStore<M> + Send + Sync,
+{
+    tree: TreeView<FlatMsg<M>, FlatStore<S>>,
+}
+
+#[async_trait]
+impl<M, S> Widget for FlatView<M, S>
+where
+    M: Msg + ChatMsg + Send + Sync + 'static,
+    M::Id: Send + Sync,
+    S: MsgStore<M> + Send + Sync + 'static,
+    S::Error: fmt::Display,
+{
+    fn size(&self, frame: &mut Frame, max_width: Option<u16>, max_height: Option<u16>) -> Size {
+        self.tree.size(frame, max_width, max_height)
+    }
+
+    async fn render(self: Box<Self>, frame: &mut Frame) {
+        Box::new(self.tree).render(frame).await
+    }
+}
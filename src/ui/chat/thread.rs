@@ -0,0 +1,477 @@
+//! Showing a single subtree on its own.
+//!
+//! The thread view reuses the tree view, but restricts the store it operates on
+//! to the messages of a single subtree.
+
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use crossterm::style::{ContentStyle, Stylize};
+use parking_lot::FairMutex;
+use time::OffsetDateTime;
+use toss::frame::{Frame, Size};
+use toss::styled::Styled;
+use toss::terminal::Terminal;
+
+use crate::config::{Keys, Timestamps};
+use crate::macros::logging_unwrap;
+use crate::store::{Msg, MsgStore, Path, Tree};
+use crate::ui::input::{InputEvent, KeyBindingsList};
+use crate::ui::widgets::editor::EditorState;
+use crate::ui::widgets::join::{Segment, VJoin};
+use crate::ui::widgets::rules::HRule;
+use crate::ui::widgets::text::Text;
+use crate::ui::widgets::Widget;
+
+use super::tree::{TreeView, TreeViewState};
+use super::{ChatMsg, Reaction};
+
+/////////////
+// Message //
+/////////////
+
+/// A message of the thread.
+///
+/// The thread's root may have a parent outside of the thread. Since the tree
+/// view should treat it like the root of a tree, it has no parent here.
+pub struct ThreadMsg<M> {
+    msg: M,
+    root: bool,
+}
+
+impl<M: Msg> Msg for ThreadMsg<M> {
+    type Id = M::Id;
+
+    fn id(&self) -> Self::Id {
+        self.msg.id()
+    }
+
+    fn parent(&self) -> Option<Self::Id> {
+        if self.root {
+            None
+        } else {
+            self.msg.parent()
+        }
+    }
+
+    fn seen(&self) -> bool {
+        self.msg.seen()
+    }
+
+    fn last_possible_id() -> Self::Id {
+        M::last_possible_id()
+    }
+}
+
+impl<M: ChatMsg> ChatMsg for ThreadMsg<M> {
+    fn time(&self) -> OffsetDateTime {
+        self.msg.time()
+    }
+
+    fn mentions(&self, nick: &str) -> bool {
+        self.msg.mentions(nick)
+    }
+
+    fn styled(&self, own_nick: &str) -> (Styled, Styled) {
+        self.msg.styled(own_nick)
+    }
+
+    fn edit(nick: &str, content: &str) -> (Styled, Styled) {
+        M::edit(nick, content)
+    }
+}
+
+///////////
+// Store //
+///////////
+
+/// A [`MsgStore`] containing only the subtree starting at `root`.
+///
+/// To the tree view, the subtree looks like a room with only a single tree.
+pub struct ThreadStore<M: Msg, S> {
+    store: S,
+    root: M::Id,
+}
+
+impl<M: Msg, S: Clone> Clone for ThreadStore<M, S> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            root: self.root.clone(),
+        }
+    }
+}
+
+impl<M: Msg, S: MsgStore<M>> ThreadStore<M, S> {
+    fn thread_msg(&self, msg: M) -> ThreadMsg<M> {
+        let root = msg.id() == self.root;
+        ThreadMsg { msg, root }
+    }
+
+    async fn thread(&self) -> Result<Tree<ThreadMsg<M>>, S::Error> {
+        let path = self.store.path(&self.root).await?;
+        let tree = self.store.tree(path.first()).await?;
+        let msgs = tree
+            .into_subtree_msgs(&self.root)
+            .into_iter()
+            .map(|msg| self.thread_msg(msg))
+            .collect();
+        Ok(Tree::new(self.root.clone(), msgs))
+    }
+
+    /// Ids of all messages in the thread, sorted from oldest to newest.
+    async fn msg_ids(&self, only_unseen: bool) -> Result<Vec<M::Id>, S::Error> {
+        let thread = self.thread().await?;
+        let mut ids = thread
+            .msgs()
+            .filter(|m| !only_unseen || !m.seen())
+            .map(|m| m.id())
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        Ok(ids)
+    }
+
+    async fn older_id(&self, id: &M::Id, only_unseen: bool) -> Result<Option<M::Id>, S::Error> {
+        let ids = self.msg_ids(only_unseen).await?;
+        Ok(ids.into_iter().rev().find(|i| i < id))
+    }
+
+    async fn newer_id(&self, id: &M::Id, only_unseen: bool) -> Result<Option<M::Id>, S::Error> {
+        let ids = self.msg_ids(only_unseen).await?;
+        Ok(ids.into_iter().find(|i| i > id))
+    }
+}
+
+#[async_trait]
+impl<M, S> MsgStore<ThreadMsg<M>> for ThreadStore<M, S>
+where
+    M: Msg + Send + Sync,
+    M::Id: Send + Sync,
+    S: MsgStore<M> + Send + Sync,
+{
+    type Error = S::Error;
+
+    async fn path(&self, id: &M::Id) -> Result<Path<M::Id>, Self::Error> {
+        let path = self.store.path(id).await?;
+        let segments = path.into_iter().collect::<Vec<_>>();
+        let segments = match segments.iter().position(|s| *s == self.root) {
+            Some(i) => segments[i..].to_vec(),
+            None => segments,
+        };
+        Ok(Path::new(segments))
+    }
+
+    async fn msg(&self, id: &M::Id) -> Result<Option<ThreadMsg<M>>, Self::Error> {
+        let msg = self.store.msg(id).await?;
+        Ok(msg.map(|msg| self.thread_msg(msg)))
+    }
+
+    async fn tree(&self, root_id: &M::Id) -> Result<Tree<ThreadMsg<M>>, Self::Error> {
+        if *root_id == self.root {
+            self.thread().await
+        } else {
+            let tree = self.store.tree(root_id).await?;
+            let msgs = tree
+                .into_subtree_msgs(root_id)
+                .into_iter()
+                .map(|msg| self.thread_msg(msg))
+                .collect();
+            Ok(Tree::new(root_id.clone(), msgs))
+        }
+    }
+
+    async fn first_root_id(&self) -> Result<Option<M::Id>, Self::Error> {
+        Ok(Some(self.root.clone()))
+    }
+
+    async fn last_root_id(&self) -> Result<Option<M::Id>, Self::Error> {
+        Ok(Some(self.root.clone()))
+    }
+
+    async fn prev_root_id(&self, _root_id: &M::Id) -> Result<Option<M::Id>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn next_root_id(&self, _root_id: &M::Id) -> Result<Option<M::Id>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn oldest_msg_id(&self) -> Result<Option<M::Id>, Self::Error> {
+        Ok(self.msg_ids(false).await?.into_iter().next())
+    }
+
+    async fn newest_msg_id(&self) -> Result<Option<M::Id>, Self::Error> {
+        Ok(self.msg_ids(false).await?.into_iter().next_back())
+    }
+
+    async fn older_msg_id(&self, id: &M::Id) -> Result<Option<M::Id>, Self::Error> {
+        self.older_id(id, false).await
+    }
+
+    async fn newer_msg_id(&self, id: &M::Id) -> Result<Option<M::Id>, Self::Error> {
+        self.newer_id(id, false).await
+    }
+
+    async fn oldest_unseen_msg_id(&self) -> Result<Option<M::Id>, Self::Error> {
+        Ok(self.msg_ids(true).await?.into_iter().next())
+    }
+
+    async fn newest_unseen_msg_id(&self) -> Result<Option<M::Id>, Self::Error> {
+        Ok(self.msg_ids(true).await?.into_iter().next_back())
+    }
+
+    async fn older_unseen_msg_id(&self, id: &M::Id) -> Result<Option<M::Id>, Self::Error> {
+        self.older_id(id, true).await
+    }
+
+    async fn newer_unseen_msg_id(&self, id: &M::Id) -> Result<Option<M::Id>, Self::Error> {
+        self.newer_id(id, true).await
+    }
+
+    async fn older_mention_msg_id(
+        &self,
+        id: &M::Id,
+        nick: &str,
+    ) -> Result<Option<M::Id>, Self::Error> {
+        // Mentions are searched in the whole room and then filtered, so the
+        // search can stop once it has left the thread.
+        let ids = self.msg_ids(false).await?;
+        let mut id = id.clone();
+        while let Some(mention_id) = self.store.older_mention_msg_id(&id, nick).await? {
+            match ids.first() {
+                Some(oldest) if mention_id >= *oldest => {}
+                _ => return Ok(None),
+            }
+            if ids.binary_search(&mention_id).is_ok() {
+                return Ok(Some(mention_id));
+            }
+            id = mention_id;
+        }
+        Ok(None)
+    }
+
+    async fn newer_mention_msg_id(
+        &self,
+        id: &M::Id,
+        nick: &str,
+    ) -> Result<Option<M::Id>, Self::Error> {
+        let ids = self.msg_ids(false).await?;
+        let mut id = id.clone();
+        while let Some(mention_id) = self.store.newer_mention_msg_id(&id, nick).await? {
+            match ids.last() {
+                Some(newest) if mention_id <= *newest => {}
+                _ => return Ok(None),
+            }
+            if ids.binary_search(&mention_id).is_ok() {
+                return Ok(Some(mention_id));
+            }
+            id = mention_id;
+        }
+        Ok(None)
+    }
+
+    async fn gaps(&self) -> Result<Vec<(M::Id, M::Id)>, Self::Error> {
+        // Only gaps between the oldest and newest message of the thread may
+        // hide messages inside the thread.
+        let ids = self.msg_ids(false).await?;
+        let (Some(oldest), Some(newest)) = (ids.first(), ids.last()) else {
+            return Ok(vec![]);
+        };
+        let gaps = self.store.gaps().await?;
+        Ok(gaps
+            .into_iter()
+            .filter(|(start, end)| end > oldest && start < newest)
+            .collect())
+    }
+
+    async fn unseen_msgs_count(&self) -> Result<usize, Self::Error> {
+        Ok(self.msg_ids(true).await?.len())
+    }
+
+    async fn draft(&self, parent: Option<&M::Id>) -> Result<Option<String>, Self::Error> {
+        self.store.draft(parent).await
+    }
+
+    async fn set_draft(&self, parent: Option<&M::Id>, content: &str) -> Result<(), Self::Error> {
+        self.store.set_draft(parent, content).await
+    }
+
+    async fn set_seen(&self, id: &M::Id, seen: bool) -> Result<(), Self::Error> {
+        self.store.set_seen(id, seen).await
+    }
+
+    async fn set_older_seen(&self, id: &M::Id, seen: bool) -> Result<(), Self::Error> {
+        // Only messages inside the thread should be affected.
+        let ids = self.msg_ids(false).await?;
+        for older_id in ids {
+            if older_id <= *id {
+                self.store.set_seen(&older_id, seen).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+///////////
+// State //
+///////////
+
+pub struct ThreadViewState<M, S>
+where
+    M: Msg + Send + Sync,
+    M::Id: Send + Sync,
+    S: MsgStore<M> + Send + Sync,
+{
+    store: ThreadStore<M, S>,
+    tree: TreeViewState<ThreadMsg<M>, ThreadStore<M, S>>,
+}
+
+impl<M, S> ThreadViewState<M, S>
+where
+    M: Msg + Send + Sync,
+    M::Id: Send + Sync,
+    S: MsgStore<M> + Clone + Send + Sync,
+{
+    pub fn new(
+        store: S,
+        root: M::Id,
+        keys: &'static Keys,
+        timestamps: &'static Timestamps,
+    ) -> Self {
+        let store = ThreadStore { store, root };
+        Self {
+            tree: TreeViewState::new(store.clone(), keys, timestamps),
+            store,
+        }
+    }
+
+    pub fn root(&self) -> &M::Id {
+        &self.store.root
+    }
+
+    pub fn widget(&self, nick: String, focused: bool) -> ThreadView<M, S> {
+        ThreadView {
+            store: self.store.clone(),
+            tree: self.tree.widget(nick, focused),
+        }
+    }
+
+    pub async fn list_key_bindings(&self, bindings: &mut KeyBindingsList, can_compose: bool) {
+        self.tree.list_key_bindings(bindings, can_compose).await;
+    }
+
+    pub async fn handle_input_event(
+        &mut self,
+        terminal: &mut Terminal,
+        crossterm_lock: &Arc<FairMutex<()>>,
+        event: &InputEvent,
+        can_compose: bool,
+    ) -> Result<Reaction<M>, S::Error> {
+        let reaction = self
+            .tree
+            .handle_input_event(terminal, crossterm_lock, event, can_compose)
+            .await?;
+
+        Ok(match reaction {
+            Reaction::NotHandled => Reaction::NotHandled,
+            Reaction::Handled => Reaction::Handled,
+            // New threads started from inside the thread view would otherwise
+            // end up outside of the thread.
+            Reaction::Composed { parent, content } => Reaction::Composed {
+                parent: Some(parent.unwrap_or_else(|| self.store.root.clone())),
+                content,
+            },
+            Reaction::ComposeError(err) => Reaction::ComposeError(err),
+        })
+    }
+
+    pub async fn composing(&self) -> bool {
+        self.tree.composing().await
+    }
+
+    pub async fn editor(&self) -> Option<EditorState> {
+        self.tree.editor().await
+    }
+
+    pub async fn cursor(&self) -> Option<M::Id> {
+        self.tree.cursor().await
+    }
+
+    pub async fn save_draft(&self, immediately: bool) -> Result<(), S::Error> {
+        self.tree.save_draft(immediately).await
+    }
+
+    pub async fn sent(&mut self, id: Option<M::Id>) -> Result<(), S::Error> {
+        self.tree.sent(id).await
+    }
+
+    pub async fn jump_to(&mut self, id: M::Id) -> Result<(), S::Error> {
+        self.tree.jump_to(id).await
+    }
+}
+
+////////////
+// Widget //
+////////////
+
+pub struct ThreadView<M, S>
+where
+    M: Msg + Send + Sync,
+    M::Id: Send + Sync,
+    S: MsgStore<M> + Send + Sync,
+{
+    store: ThreadStore<M, S>,
+    tree: TreeView<ThreadMsg<M>, ThreadStore<M, S>>,
+}
+
+impl<M, S> ThreadView<M, S>
+where
+    M: Msg + ChatMsg + Send + Sync,
+    M::Id: Send + Sync,
+    S: MsgStore<M> + Send + Sync,
+    S::Error: fmt::Display,
+{
+    /// The thread root's ancestors followed by the thread root itself.
+    async fn breadcrumb(&self) -> Styled {
+        let separator_style = ContentStyle::default().grey();
+        let mut result = Styled::new("Thread:", ContentStyle::default().bold());
+
+        let path = logging_unwrap!(self.store.store.path(&self.store.root).await);
+        for (i, id) in path.into_iter().enumerate() {
+            if i > 0 {
+                result = result.then(" ›", separator_style);
+            }
+            result = result.then_plain(" ");
+            result = match logging_unwrap!(self.store.store.msg(&id).await) {
+                Some(msg) => result.and_then(msg.styled("").0),
+                None => result.then("[...]", separator_style),
+            };
+        }
+
+        result
+    }
+}
+
+#[async_trait]
+impl<M, S> Widget for ThreadView<M, S>
+where
+    M: Msg + ChatMsg + Send + Sync + 'static,
+    M::Id: Send + Sync,
+    S: MsgStore<M> + Send + Sync + 'static,
+    S::Error: fmt::Display,
+{
+    fn size(&self, _frame: &mut Frame, _max_width: Option<u16>, _max_height: Option<u16>) -> Size {
+        Size::ZERO
+    }
+
+    async fn render(self: Box<Self>, frame: &mut Frame) {
+        let breadcrumb = self.breadcrumb().await;
+        let widget = VJoin::new(vec![
+            Segment::new(Text::new(breadcrumb)),
+            Segment::new(HRule),
+            Segment::new(self.tree).expanding(true),
+        ]);
+        Box::new(widget).render(frame).await;
+    }
+}
@@ -1,5 +1,3 @@
-// TODO Focusing on sub-trees
-
 mod cursor;
 mod layout;
 mod tree_blocks;
@@ -8,13 +6,16 @@ mod widgets;
 use std::collections::HashSet;
 use std::fmt;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use parking_lot::FairMutex;
 use tokio::sync::Mutex;
 use toss::frame::{Frame, Pos, Size};
 use toss::terminal::Terminal;
+use toss::widthdb::WidthDb;
 
+use crate::config::{Keys, Timestamps};
 use crate::macros::logging_unwrap;
 use crate::store::{Msg, MsgStore};
 use crate::ui::input::{key, InputEvent, KeyBindingsList};
@@ -30,6 +31,9 @@ use super::{ChatMsg, Reaction};
 // State //
 ///////////
 
+/// How often the draft is saved at most while typing.
+pub const DRAFT_SAVE_INTERVAL: Duration = Duration::from_secs(2);
+
 enum Correction {
     MakeCursorVisible,
     MoveCursorToVisibleArea,
@@ -38,6 +42,12 @@ enum Correction {
 
 struct InnerTreeViewState<M: Msg, S: MsgStore<M>> {
     store: S,
+    keys: &'static Keys,
+    timestamps: &'static Timestamps,
+
+    /// The nick used when the view was last rendered. Used to find messages
+    /// mentioning it.
+    nick: String,
 
     last_cursor: Cursor<M::Id>,
     last_cursor_line: i32,
@@ -45,6 +55,9 @@ struct InnerTreeViewState<M: Msg, S: MsgStore<M>> {
 
     cursor: Cursor<M::Id>,
     editor: EditorState,
+    /// The draft as it was last saved to the store, and when.
+    saved_draft: String,
+    draft_saved_at: Instant,
 
     /// Scroll the view on the next render. Positive values scroll up and
     /// negative values scroll down.
@@ -55,14 +68,19 @@ struct InnerTreeViewState<M: Msg, S: MsgStore<M>> {
 }
 
 impl<M: Msg, S: MsgStore<M>> InnerTreeViewState<M, S> {
-    fn new(store: S) -> Self {
+    fn new(store: S, keys: &'static Keys, timestamps: &'static Timestamps) -> Self {
         Self {
             store,
+            keys,
+            timestamps,
+            nick: String::new(),
             last_cursor: Cursor::Bottom,
             last_cursor_line: 0,
             last_visible_msgs: vec![],
             cursor: Cursor::Bottom,
             editor: EditorState::new(),
+            saved_draft: String::new(),
+            draft_saved_at: Instant::now(),
             scroll: 0,
             correction: None,
             folded: HashSet::new(),
@@ -70,17 +88,51 @@ impl<M: Msg, S: MsgStore<M>> InnerTreeViewState<M, S> {
     }
 
     pub fn list_movement_key_bindings(&self, bindings: &mut KeyBindingsList) {
-        bindings.binding("j/k, ↓/↑", "move cursor up/down");
-        bindings.binding("J/K, ctrl+↓/↑", "move cursor to prev/next sibling");
-        bindings.binding("p/P", "move cursor to parent/root");
-        bindings.binding("h/l, ←/→", "move cursor chronologically");
-        bindings.binding("H/L, ctrl+←/→", "move cursor to prev/next unseen message");
-        bindings.binding("g, home", "move cursor to top");
-        bindings.binding("G, end", "move cursor to bottom");
-        bindings.binding("ctrl+y/e", "scroll up/down a line");
-        bindings.binding("ctrl+u/d", "scroll up/down half a screen");
-        bindings.binding("ctrl+b/f, page up/down", "scroll up/down one screen");
-        bindings.binding("z", "center cursor on screen");
+        let keys = self.keys;
+        bindings.action_pair(&keys.cursor.down, &keys.cursor.up, "move cursor up/down");
+        bindings.action_pair(
+            &keys.cursor.down_sibling,
+            &keys.cursor.up_sibling,
+            "move cursor to prev/next sibling",
+        );
+        bindings.action_pair(
+            &keys.cursor.to_parent,
+            &keys.cursor.to_root,
+            "move cursor to parent/root",
+        );
+        bindings.action_pair(
+            &keys.cursor.older,
+            &keys.cursor.newer,
+            "move cursor chronologically",
+        );
+        bindings.action_pair(
+            &keys.cursor.older_unseen,
+            &keys.cursor.newer_unseen,
+            "move cursor to prev/next unseen message",
+        );
+        bindings.action_pair(
+            &keys.cursor.older_mention,
+            &keys.cursor.newer_mention,
+            "move cursor to prev/next message mentioning you",
+        );
+        bindings.action(&keys.cursor.to_top, "move cursor to top");
+        bindings.action(&keys.cursor.to_bottom, "move cursor to bottom");
+        bindings.action_pair(
+            &keys.scroll.up_line,
+            &keys.scroll.down_line,
+            "scroll up/down a line",
+        );
+        bindings.action_pair(
+            &keys.scroll.up_half,
+            &keys.scroll.down_half,
+            "scroll up/down half a screen",
+        );
+        bindings.action_pair(
+            &keys.scroll.up_full,
+            &keys.scroll.down_full,
+            "scroll up/down one screen",
+        );
+        bindings.action(&keys.scroll.center_cursor, "center cursor on screen");
         // TODO Bindings inspired by vim's ()/[]/{} bindings?
     }
 
@@ -89,41 +141,73 @@ impl<M: Msg, S: MsgStore<M>> InnerTreeViewState<M, S> {
         frame: &mut Frame,
         event: &InputEvent,
     ) -> Result<bool, S::Error> {
+        let keys = self.keys;
         let chat_height = frame.size().height - 3;
 
-        match event {
-            key!('k') | key!(Up) => self.move_cursor_up().await?,
-            key!('j') | key!(Down) => self.move_cursor_down().await?,
-            key!('K') | key!(Ctrl + Up) => self.move_cursor_up_sibling().await?,
-            key!('J') | key!(Ctrl + Down) => self.move_cursor_down_sibling().await?,
-            key!('p') => self.move_cursor_to_parent().await?,
-            key!('P') => self.move_cursor_to_root().await?,
-            key!('h') | key!(Left) => self.move_cursor_older().await?,
-            key!('l') | key!(Right) => self.move_cursor_newer().await?,
-            key!('H') | key!(Ctrl + Left) => self.move_cursor_older_unseen().await?,
-            key!('L') | key!(Ctrl + Right) => self.move_cursor_newer_unseen().await?,
-            key!('g') | key!(Home) => self.move_cursor_to_top().await?,
-            key!('G') | key!(End) => self.move_cursor_to_bottom().await,
-            key!(Ctrl + 'y') => self.scroll_up(1),
-            key!(Ctrl + 'e') => self.scroll_down(1),
-            key!(Ctrl + 'u') => self.scroll_up((chat_height / 2).into()),
-            key!(Ctrl + 'd') => self.scroll_down((chat_height / 2).into()),
-            key!(Ctrl + 'b') | key!(PageUp) => self.scroll_up(chat_height.saturating_sub(1).into()),
-            key!(Ctrl + 'f') | key!(PageDown) => {
-                self.scroll_down(chat_height.saturating_sub(1).into())
-            }
-            key!('z') => self.center_cursor(),
-            _ => return Ok(false),
+        if keys.cursor.up.matches(event) {
+            self.move_cursor_up().await?;
+        } else if keys.cursor.down.matches(event) {
+            self.move_cursor_down().await?;
+        } else if keys.cursor.up_sibling.matches(event) {
+            self.move_cursor_up_sibling().await?;
+        } else if keys.cursor.down_sibling.matches(event) {
+            self.move_cursor_down_sibling().await?;
+        } else if keys.cursor.to_parent.matches(event) {
+            self.move_cursor_to_parent().await?;
+        } else if keys.cursor.to_root.matches(event) {
+            self.move_cursor_to_root().await?;
+        } else if keys.cursor.older.matches(event) {
+            self.move_cursor_older().await?;
+        } else if keys.cursor.newer.matches(event) {
+            self.move_cursor_newer().await?;
+        } else if keys.cursor.older_unseen.matches(event) {
+            self.move_cursor_older_unseen().await?;
+        } else if keys.cursor.newer_unseen.matches(event) {
+            self.move_cursor_newer_unseen().await?;
+        } else if keys.cursor.older_mention.matches(event) {
+            self.move_cursor_older_mention().await?;
+        } else if keys.cursor.newer_mention.matches(event) {
+            self.move_cursor_newer_mention().await?;
+        } else if keys.cursor.to_top.matches(event) {
+            self.move_cursor_to_top().await?;
+        } else if keys.cursor.to_bottom.matches(event) {
+            self.move_cursor_to_bottom().await;
+        } else if keys.scroll.up_line.matches(event) {
+            self.scroll_up(1);
+        } else if keys.scroll.down_line.matches(event) {
+            self.scroll_down(1);
+        } else if keys.scroll.up_half.matches(event) {
+            self.scroll_up((chat_height / 2).into());
+        } else if keys.scroll.down_half.matches(event) {
+            self.scroll_down((chat_height / 2).into());
+        } else if keys.scroll.up_full.matches(event) {
+            self.scroll_up(chat_height.saturating_sub(1).into());
+        } else if keys.scroll.down_full.matches(event) {
+            self.scroll_down(chat_height.saturating_sub(1).into());
+        } else if keys.scroll.center_cursor.matches(event) {
+            self.center_cursor();
+        } else {
+            return Ok(false);
         }
 
         Ok(true)
     }
 
     pub fn list_action_key_bindings(&self, bindings: &mut KeyBindingsList) {
-        bindings.binding("space", "fold current message's subtree");
-        bindings.binding("s", "toggle current message's seen status");
-        bindings.binding("S", "mark all visible messages as seen");
-        bindings.binding("ctrl+s", "mark all older messages as seen");
+        let keys = self.keys;
+        bindings.action(&keys.tree.fold, "fold current message's subtree");
+        bindings.action(
+            &keys.tree.toggle_seen,
+            "toggle current message's seen status",
+        );
+        bindings.action(
+            &keys.tree.mark_visible_seen,
+            "mark all visible messages as seen",
+        );
+        bindings.action(
+            &keys.tree.mark_older_seen,
+            "mark all older messages as seen",
+        );
     }
 
     async fn handle_action_input_event(
@@ -131,73 +215,111 @@ impl<M: Msg, S: MsgStore<M>> InnerTreeViewState<M, S> {
         event: &InputEvent,
         id: Option<&M::Id>,
     ) -> Result<bool, S::Error> {
-        match event {
-            key!(' ') => {
-                if let Some(id) = id {
-                    if !self.folded.remove(id) {
-                        self.folded.insert(id.clone());
-                    }
-                    return Ok(true);
-                }
-            }
-            key!('s') => {
-                if let Some(id) = id {
-                    if let Some(msg) = self.store.tree(id).await?.msg(id) {
-                        self.store.set_seen(id, !msg.seen()).await?;
-                    }
-                    return Ok(true);
-                }
-            }
-            key!('S') => {
-                for id in &self.last_visible_msgs {
-                    self.store.set_seen(id, true).await?;
+        let keys = self.keys;
+        if keys.tree.fold.matches(event) {
+            if let Some(id) = id {
+                if !self.folded.remove(id) {
+                    self.folded.insert(id.clone());
                 }
                 return Ok(true);
             }
-            key!(Ctrl + 's') => {
-                if let Some(id) = id {
-                    self.store.set_older_seen(id, true).await?;
-                } else {
-                    self.store
-                        .set_older_seen(&M::last_possible_id(), true)
-                        .await?;
+        } else if keys.tree.toggle_seen.matches(event) {
+            if let Some(id) = id {
+                if let Some(msg) = self.store.tree(id).await?.msg(id) {
+                    self.store.set_seen(id, !msg.seen()).await?;
                 }
                 return Ok(true);
             }
-            _ => {}
+        } else if keys.tree.mark_visible_seen.matches(event) {
+            for id in &self.last_visible_msgs {
+                self.store.set_seen(id, true).await?;
+            }
+            return Ok(true);
+        } else if keys.tree.mark_older_seen.matches(event) {
+            if let Some(id) = id {
+                self.store.set_older_seen(id, true).await?;
+            } else {
+                self.store
+                    .set_older_seen(&M::last_possible_id(), true)
+                    .await?;
+            }
+            return Ok(true);
         }
         Ok(false)
     }
 
     pub fn list_edit_initiating_key_bindings(&self, bindings: &mut KeyBindingsList) {
-        bindings.binding("r", "reply to message (inline if possible, else directly)");
-        bindings.binding("R", "reply to message (opposite of R)");
-        bindings.binding("t", "start a new thread");
+        let keys = self.keys;
+        bindings.action(
+            &keys.tree.reply,
+            "reply to message (inline if possible, else directly)",
+        );
+        bindings.action(
+            &keys.tree.reply_alternate,
+            &format!("reply to message (opposite of {})", keys.tree.reply),
+        );
+        bindings.action(&keys.tree.new_thread, "start a new thread");
+    }
+
+    /// Open the editor, restoring the draft previously composed for `parent`.
+    async fn open_editor(
+        &mut self,
+        widthdb: &mut WidthDb,
+        coming_from: Option<M::Id>,
+        parent: Option<M::Id>,
+    ) -> Result<(), S::Error> {
+        let draft = self.store.draft(parent.as_ref()).await?.unwrap_or_default();
+        self.editor.clear();
+        self.editor.insert_str(widthdb, &draft);
+        self.saved_draft = draft;
+        self.draft_saved_at = Instant::now();
+        self.cursor = Cursor::editor(coming_from, parent);
+        self.correction = Some(Correction::MakeCursorVisible);
+        Ok(())
+    }
+
+    /// Save the draft currently being composed if it has changed.
+    ///
+    /// Unless `immediately` is set, the draft is saved at most once every
+    /// [`DRAFT_SAVE_INTERVAL`] so typing doesn't write to the store on every
+    /// key press. Every change to the editor's contents should be followed by
+    /// a call to this function.
+    async fn save_draft(&mut self, immediately: bool) -> Result<(), S::Error> {
+        let Cursor::Editor { parent, .. } = &self.cursor else {
+            return Ok(());
+        };
+        if !immediately && self.draft_saved_at.elapsed() < DRAFT_SAVE_INTERVAL {
+            return Ok(());
+        }
+
+        let text = self.editor.text();
+        if text != self.saved_draft {
+            self.store.set_draft(parent.as_ref(), &text).await?;
+            self.saved_draft = text;
+        }
+        self.draft_saved_at = Instant::now();
+        Ok(())
     }
 
     async fn handle_edit_initiating_input_event(
         &mut self,
+        frame: &mut Frame,
         event: &InputEvent,
         id: Option<M::Id>,
     ) -> Result<bool, S::Error> {
-        match event {
-            key!('r') => {
-                if let Some(parent) = self.parent_for_normal_reply().await? {
-                    self.cursor = Cursor::editor(id, parent);
-                    self.correction = Some(Correction::MakeCursorVisible);
-                }
+        let keys = self.keys;
+        if keys.tree.reply.matches(event) {
+            if let Some(parent) = self.parent_for_normal_reply().await? {
+                self.open_editor(frame.widthdb(), id, parent).await?;
             }
-            key!('R') => {
-                if let Some(parent) = self.parent_for_alternate_reply().await? {
-                    self.cursor = Cursor::editor(id, parent);
-                    self.correction = Some(Correction::MakeCursorVisible);
-                }
-            }
-            key!('t') | key!('T') => {
-                self.cursor = Cursor::editor(id, None);
-                self.correction = Some(Correction::MakeCursorVisible);
+        } else if keys.tree.reply_alternate.matches(event) {
+            if let Some(parent) = self.parent_for_alternate_reply().await? {
+                self.open_editor(frame.widthdb(), id, parent).await?;
             }
-            _ => return Ok(false),
+        } else if keys.tree.new_thread.matches(event) {
+            self.open_editor(frame.widthdb(), id, None).await?;
+        } else {
+            return Ok(false);
         }
 
         Ok(true)
@@ -226,7 +348,8 @@ impl<M: Msg, S: MsgStore<M>> InnerTreeViewState<M, S> {
         } else if self.handle_action_input_event(event, id.as_ref()).await? {
             true
         } else if can_compose {
-            self.handle_edit_initiating_input_event(event, id).await?
+            self.handle_edit_initiating_input_event(frame, event, id)
+                .await?
         } else {
             false
         })
@@ -238,30 +361,32 @@ impl<M: Msg, S: MsgStore<M>> InnerTreeViewState<M, S> {
         util::list_editor_key_bindings_allowing_external_editing(bindings, |_| true);
     }
 
-    fn handle_editor_input_event(
+    async fn handle_editor_input_event(
         &mut self,
         terminal: &mut Terminal,
         crossterm_lock: &Arc<FairMutex<()>>,
         event: &InputEvent,
         coming_from: Option<M::Id>,
         parent: Option<M::Id>,
-    ) -> Reaction<M> {
-        // TODO Tab-completion
+    ) -> Result<Reaction<M>, S::Error> {
         match event {
             key!(Esc) => {
+                self.save_draft(true).await?;
                 self.cursor = coming_from.map(Cursor::Msg).unwrap_or(Cursor::Bottom);
                 self.correction = Some(Correction::MakeCursorVisible);
-                return Reaction::Handled;
+                return Ok(Reaction::Handled);
             }
 
             key!(Enter) => {
                 let content = self.editor.text();
                 if !content.trim().is_empty() {
+                    // If sending fails, the draft must not be lost.
+                    self.save_draft(true).await?;
                     self.cursor = Cursor::Pseudo {
                         coming_from,
                         parent: parent.clone(),
                     };
-                    return Reaction::Composed { parent, content };
+                    return Ok(Reaction::Composed { parent, content });
                 }
             }
 
@@ -275,14 +400,16 @@ impl<M: Msg, S: MsgStore<M>> InnerTreeViewState<M, S> {
                 );
                 match handled {
                     Ok(true) => {}
-                    Ok(false) => return Reaction::NotHandled,
-                    Err(e) => return Reaction::ComposeError(e),
+                    Ok(false) => return Ok(Reaction::NotHandled),
+                    Err(e) => return Ok(Reaction::ComposeError(e)),
                 }
+
+                self.save_draft(false).await?;
             }
         }
 
         self.correction = Some(Correction::MakeCursorVisible);
-        Reaction::Handled
+        Ok(Reaction::Handled)
     }
 
     pub fn list_key_bindings(&self, bindings: &mut KeyBindingsList, can_compose: bool) {
@@ -330,13 +457,16 @@ impl<M: Msg, S: MsgStore<M>> InnerTreeViewState<M, S> {
             Cursor::Editor {
                 coming_from,
                 parent,
-            } => self.handle_editor_input_event(
-                terminal,
-                crossterm_lock,
-                event,
-                coming_from.clone(),
-                parent.clone(),
-            ),
+            } => {
+                self.handle_editor_input_event(
+                    terminal,
+                    crossterm_lock,
+                    event,
+                    coming_from.clone(),
+                    parent.clone(),
+                )
+                .await?
+            }
             Cursor::Pseudo { .. } => {
                 if self
                     .handle_movement_input_event(terminal.frame(), event)
@@ -357,9 +487,15 @@ impl<M: Msg, S: MsgStore<M>> InnerTreeViewState<M, S> {
         }
     }
 
-    fn sent(&mut self, id: Option<M::Id>) {
-        if let Cursor::Pseudo { coming_from, .. } = &self.cursor {
+    async fn sent(&mut self, id: Option<M::Id>) -> Result<(), S::Error> {
+        if let Cursor::Pseudo {
+            coming_from,
+            parent,
+        } = &self.cursor
+        {
             if let Some(id) = id {
+                self.store.set_draft(parent.as_ref(), "").await?;
+                self.saved_draft.clear();
                 self.last_cursor = Cursor::Msg(id.clone());
                 self.cursor = Cursor::Msg(id);
                 self.editor.clear();
@@ -370,14 +506,17 @@ impl<M: Msg, S: MsgStore<M>> InnerTreeViewState<M, S> {
                 };
             };
         }
+        Ok(())
     }
 }
 
 pub struct TreeViewState<M: Msg, S: MsgStore<M>>(Arc<Mutex<InnerTreeViewState<M, S>>>);
 
 impl<M: Msg, S: MsgStore<M>> TreeViewState<M, S> {
-    pub fn new(store: S) -> Self {
-        Self(Arc::new(Mutex::new(InnerTreeViewState::new(store))))
+    pub fn new(store: S, keys: &'static Keys, timestamps: &'static Timestamps) -> Self {
+        Self(Arc::new(Mutex::new(InnerTreeViewState::new(
+            store, keys, timestamps,
+        ))))
     }
 
     pub fn widget(&self, nick: String, focused: bool) -> TreeView<M, S> {
@@ -410,8 +549,29 @@ impl<M: Msg, S: MsgStore<M>> TreeViewState<M, S> {
         self.0.lock().await.cursor()
     }
 
-    pub async fn sent(&mut self, id: Option<M::Id>) {
-        self.0.lock().await.sent(id)
+    pub async fn composing(&self) -> bool {
+        matches!(self.0.lock().await.cursor, Cursor::Editor { .. })
+    }
+
+    /// The editor, if a message is currently being composed.
+    pub async fn editor(&self) -> Option<EditorState> {
+        let guard = self.0.lock().await;
+        match guard.cursor {
+            Cursor::Editor { .. } => Some(guard.editor.clone()),
+            _ => None,
+        }
+    }
+
+    pub async fn save_draft(&self, immediately: bool) -> Result<(), S::Error> {
+        self.0.lock().await.save_draft(immediately).await
+    }
+
+    pub async fn sent(&mut self, id: Option<M::Id>) -> Result<(), S::Error> {
+        self.0.lock().await.sent(id).await
+    }
+
+    pub async fn jump_to(&mut self, id: M::Id) -> Result<(), S::Error> {
+        self.0.lock().await.move_cursor_to_msg(id).await
     }
 }
 
@@ -412,6 +412,37 @@ impl<M: Msg, S: MsgStore<M>> InnerTreeViewState<M, S> {
         Ok(())
     }
 
+    pub async fn move_cursor_older_mention(&mut self) -> Result<(), S::Error> {
+        let id = match &self.cursor {
+            Cursor::Msg(id) => id.clone(),
+            Cursor::Bottom | Cursor::Pseudo { .. } => M::last_possible_id(),
+            Cursor::Editor { .. } => return Ok(()),
+        };
+        if let Some(id) = self.store.older_mention_msg_id(&id, &self.nick).await? {
+            self.cursor = Cursor::Msg(id);
+        }
+        self.correction = Some(Correction::MakeCursorVisible);
+        Ok(())
+    }
+
+    pub async fn move_cursor_newer_mention(&mut self) -> Result<(), S::Error> {
+        match &mut self.cursor {
+            Cursor::Msg(id) => {
+                if let Some(next_id) = self.store.newer_mention_msg_id(id, &self.nick).await? {
+                    *id = next_id;
+                } else {
+                    self.cursor = Cursor::Bottom;
+                }
+            }
+            Cursor::Pseudo { .. } => {
+                self.cursor = Cursor::Bottom;
+            }
+            _ => {}
+        }
+        self.correction = Some(Correction::MakeCursorVisible);
+        Ok(())
+    }
+
     pub async fn move_cursor_to_top(&mut self) -> Result<(), S::Error> {
         if let Some(first_root_id) = self.store.first_root_id().await? {
             self.cursor = Cursor::Msg(first_root_id);
@@ -426,6 +457,18 @@ impl<M: Msg, S: MsgStore<M>> InnerTreeViewState<M, S> {
         self.correction = Some(Correction::MakeCursorVisible);
     }
 
+    pub async fn move_cursor_to_msg(&mut self, id: M::Id) -> Result<(), S::Error> {
+        // The message might be hidden inside a folded subtree
+        let path = self.store.path(&id).await?;
+        for segment in path.parent_segments() {
+            self.folded.remove(segment);
+        }
+
+        self.cursor = Cursor::Msg(id);
+        self.correction = Some(Correction::CenterCursor);
+        Ok(())
+    }
+
     pub fn scroll_up(&mut self, amount: i32) {
         self.scroll += amount;
         self.correction = Some(Correction::MoveCursorToVisibleArea);
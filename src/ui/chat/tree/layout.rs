@@ -16,9 +16,11 @@ fn scrolloff(height: i32) -> i32 {
     scrolloff.min(SCROLLOFF)
 }
 
-struct Context {
+struct Context<I> {
     nick: String,
     focused: bool,
+    /// See [`MsgStore::gaps`].
+    gaps: Vec<(I, I)>,
 }
 
 impl<M: Msg + ChatMsg, S: MsgStore<M>> InnerTreeViewState<M, S> {
@@ -71,29 +73,49 @@ impl<M: Msg + ChatMsg, S: MsgStore<M>> InnerTreeViewState<M, S> {
 
     fn editor_block(
         &self,
-        context: &Context,
+        context: &Context<M::Id>,
         frame: &mut Frame,
         indent: usize,
     ) -> Block<BlockId<M::Id>> {
-        let (widget, cursor_row) =
-            widgets::editor::<M>(frame.widthdb(), indent, &context.nick, &self.editor);
+        let (widget, cursor_row) = widgets::editor::<M>(
+            frame.widthdb(),
+            self.timestamps,
+            indent,
+            &context.nick,
+            &self.editor,
+        );
         let cursor_row = cursor_row as i32;
         Block::new(frame, BlockId::Cursor, widget).focus(cursor_row..cursor_row + 1)
     }
 
     fn pseudo_block(
         &self,
-        context: &Context,
+        context: &Context<M::Id>,
         frame: &mut Frame,
         indent: usize,
     ) -> Block<BlockId<M::Id>> {
-        let widget = widgets::pseudo::<M>(indent, &context.nick, &self.editor);
+        let widget = widgets::pseudo::<M>(self.timestamps, indent, &context.nick, &self.editor);
         Block::new(frame, BlockId::Cursor, widget)
     }
 
+    fn gap_block(&self, frame: &mut Frame, root_id: M::Id) -> Block<BlockId<M::Id>> {
+        let widget = widgets::gap(self.timestamps);
+        Block::new(frame, BlockId::Gap(root_id), widget)
+    }
+
+    /// Whether messages may be missing between two neighbouring trees.
+    ///
+    /// If there is no newer tree, the older tree is the last one in the room.
+    fn gap_between(context: &Context<M::Id>, older: &M::Id, newer: Option<&M::Id>) -> bool {
+        context
+            .gaps
+            .iter()
+            .any(|(before, after)| after > older && newer.map(|n| before < n).unwrap_or(true))
+    }
+
     fn layout_subtree(
         &self,
-        context: &Context,
+        context: &Context<M::Id>,
         frame: &mut Frame,
         tree: &Tree<M>,
         indent: usize,
@@ -117,9 +139,16 @@ impl<M: Msg + ChatMsg, S: MsgStore<M>> InnerTreeViewState<M, S> {
         // Main message body
         let highlighted = context.focused && self.cursor.refers_to(id);
         let widget = if let Some(msg) = tree.msg(id) {
-            widgets::msg(highlighted, indent, msg, folded_info)
+            widgets::msg(
+                self.timestamps,
+                &context.nick,
+                highlighted,
+                indent,
+                msg,
+                folded_info,
+            )
         } else {
-            widgets::msg_placeholder(highlighted, indent, folded_info)
+            widgets::msg_placeholder(self.timestamps, highlighted, indent, folded_info)
         };
         let block = Block::new(frame, BlockId::Msg(id.clone()), widget);
         blocks.blocks_mut().push_back(block);
@@ -159,7 +188,7 @@ impl<M: Msg + ChatMsg, S: MsgStore<M>> InnerTreeViewState<M, S> {
 
     fn layout_tree(
         &self,
-        context: &Context,
+        context: &Context<M::Id>,
         frame: &mut Frame,
         tree: Tree<M>,
     ) -> TreeBlocks<M::Id> {
@@ -169,7 +198,7 @@ impl<M: Msg + ChatMsg, S: MsgStore<M>> InnerTreeViewState<M, S> {
         blocks
     }
 
-    fn layout_bottom(&self, context: &Context, frame: &mut Frame) -> TreeBlocks<M::Id> {
+    fn layout_bottom(&self, context: &Context<M::Id>, frame: &mut Frame) -> TreeBlocks<M::Id> {
         let mut blocks = TreeBlocks::new(Root::Bottom, Root::Bottom);
 
         // Ghost cursor, for positioning according to last cursor line
@@ -199,7 +228,7 @@ impl<M: Msg + ChatMsg, S: MsgStore<M>> InnerTreeViewState<M, S> {
 
     async fn expand_to_top(
         &self,
-        context: &Context,
+        context: &Context<M::Id>,
         frame: &mut Frame,
         blocks: &mut TreeBlocks<M::Id>,
     ) -> Result<(), S::Error> {
@@ -216,7 +245,16 @@ impl<M: Msg + ChatMsg, S: MsgStore<M>> InnerTreeViewState<M, S> {
                 None => break,
             };
             let prev_tree = self.store.tree(&prev_root_id).await?;
-            blocks.prepend(self.layout_tree(context, frame, prev_tree));
+            let mut prev_blocks = self.layout_tree(context, frame, prev_tree);
+            let top_root_id = match top_root {
+                Root::Bottom => None,
+                Root::Tree(root_id) => Some(root_id),
+            };
+            if Self::gap_between(context, &prev_root_id, top_root_id) {
+                let block = self.gap_block(frame, prev_root_id);
+                prev_blocks.blocks_mut().push_back(block);
+            }
+            blocks.prepend(prev_blocks);
         }
 
         Ok(())
@@ -224,24 +262,29 @@ impl<M: Msg + ChatMsg, S: MsgStore<M>> InnerTreeViewState<M, S> {
 
     async fn expand_to_bottom(
         &self,
-        context: &Context,
+        context: &Context<M::Id>,
         frame: &mut Frame,
         blocks: &mut TreeBlocks<M::Id>,
     ) -> Result<(), S::Error> {
         let bottom_line = frame.size().height as i32 - 1;
 
         while blocks.blocks().bottom_line < bottom_line {
-            let bottom_root = blocks.bottom_root();
-            let next_root_id = match bottom_root {
+            let bottom_root_id = match blocks.bottom_root() {
                 Root::Bottom => break,
-                Root::Tree(root_id) => self.store.next_root_id(root_id).await?,
+                Root::Tree(root_id) => root_id.clone(),
             };
-            if let Some(next_root_id) = next_root_id {
-                let next_tree = self.store.tree(&next_root_id).await?;
-                blocks.append(self.layout_tree(context, frame, next_tree));
+            let next_root_id = self.store.next_root_id(&bottom_root_id).await?;
+            let mut next_blocks = if let Some(next_root_id) = &next_root_id {
+                let next_tree = self.store.tree(next_root_id).await?;
+                self.layout_tree(context, frame, next_tree)
             } else {
-                blocks.append(self.layout_bottom(context, frame));
+                self.layout_bottom(context, frame)
+            };
+            if Self::gap_between(context, &bottom_root_id, next_root_id.as_ref()) {
+                let block = self.gap_block(frame, bottom_root_id);
+                next_blocks.blocks_mut().push_front(block);
             }
+            blocks.append(next_blocks);
         }
 
         Ok(())
@@ -249,7 +292,7 @@ impl<M: Msg + ChatMsg, S: MsgStore<M>> InnerTreeViewState<M, S> {
 
     async fn fill_screen_and_clamp_scrolling(
         &self,
-        context: &Context,
+        context: &Context<M::Id>,
         frame: &mut Frame,
         blocks: &mut TreeBlocks<M::Id>,
     ) -> Result<(), S::Error> {
@@ -275,7 +318,7 @@ impl<M: Msg + ChatMsg, S: MsgStore<M>> InnerTreeViewState<M, S> {
 
     async fn layout_last_cursor_seed(
         &self,
-        context: &Context,
+        context: &Context<M::Id>,
         frame: &mut Frame,
         last_cursor_path: &Path<M::Id>,
     ) -> Result<TreeBlocks<M::Id>, S::Error> {
@@ -319,7 +362,7 @@ impl<M: Msg + ChatMsg, S: MsgStore<M>> InnerTreeViewState<M, S> {
 
     async fn layout_cursor_seed(
         &self,
-        context: &Context,
+        context: &Context<M::Id>,
         frame: &mut Frame,
         last_cursor_path: &Path<M::Id>,
         cursor_path: &Path<M::Id>,
@@ -360,7 +403,7 @@ impl<M: Msg + ChatMsg, S: MsgStore<M>> InnerTreeViewState<M, S> {
 
     async fn layout_initial_seed(
         &self,
-        context: &Context,
+        context: &Context<M::Id>,
         frame: &mut Frame,
         last_cursor_path: &Path<M::Id>,
         cursor_path: &Path<M::Id>,
@@ -538,7 +581,13 @@ impl<M: Msg + ChatMsg, S: MsgStore<M>> InnerTreeViewState<M, S> {
         //
         // This entire process is complicated by the different kinds of cursors.
 
-        let context = Context { nick, focused };
+        self.nick = nick.clone();
+        let gaps = self.store.gaps().await?;
+        let context = Context {
+            nick,
+            focused,
+            gaps,
+        };
 
         let last_cursor_path = self.cursor_path(&self.last_cursor).await?;
         let cursor_path = self.cursor_path(&self.cursor).await?;
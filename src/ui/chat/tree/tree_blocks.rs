@@ -7,6 +7,8 @@ pub enum BlockId<I> {
     Msg(I),
     Cursor,
     LastCursor,
+    /// Marker for missing messages after the tree with this root.
+    Gap(I),
 }
 
 impl<I: Clone> BlockId<I> {
@@ -7,6 +7,7 @@ use toss::styled::Styled;
 use toss::widthdb::WidthDb;
 
 use super::super::ChatMsg;
+use crate::config::Timestamps;
 use crate::store::Msg;
 use crate::ui::widgets::editor::EditorState;
 use crate::ui::widgets::join::{HJoin, Segment};
@@ -18,6 +19,7 @@ use crate::ui::widgets::BoxedWidget;
 use self::indent::Indent;
 
 pub const PLACEHOLDER: &str = "[...]";
+pub const GAP: &str = "[missing messages]";
 
 pub fn style_placeholder() -> ContentStyle {
     ContentStyle::default().dark_grey()
@@ -52,12 +54,14 @@ fn style_pseudo_highlight() -> ContentStyle {
 }
 
 pub fn msg<M: Msg + ChatMsg>(
+    timestamps: &Timestamps,
+    own_nick: &str,
     highlighted: bool,
     indent: usize,
     msg: &M,
     folded_info: Option<usize>,
 ) -> BoxedWidget {
-    let (nick, mut content) = msg.styled();
+    let (nick, mut content) = msg.styled(own_nick);
 
     if let Some(amount) = folded_info {
         content = content
@@ -66,11 +70,15 @@ pub fn msg<M: Msg + ChatMsg>(
     }
 
     HJoin::new(vec![
-        Segment::new(seen::widget(msg.seen())),
+        Segment::new(seen::widget(msg.seen(), msg.mentions(own_nick))),
         Segment::new(
-            Padding::new(time::widget(Some(msg.time()), style_time(highlighted)))
-                .stretch(true)
-                .right(1),
+            Padding::new(time::widget(
+                timestamps,
+                Some(msg.time()),
+                style_time(highlighted),
+            ))
+            .stretch(true)
+            .right(1),
         ),
         Segment::new(Indent::new(indent, style_indent(highlighted))),
         Segment::new(Layer::new(vec![
@@ -85,6 +93,7 @@ pub fn msg<M: Msg + ChatMsg>(
 }
 
 pub fn msg_placeholder(
+    timestamps: &Timestamps,
     highlighted: bool,
     indent: usize,
     folded_info: Option<usize>,
@@ -98,9 +107,9 @@ pub fn msg_placeholder(
     }
 
     HJoin::new(vec![
-        Segment::new(seen::widget(true)),
+        Segment::new(seen::widget(true, false)),
         Segment::new(
-            Padding::new(time::widget(None, style_time(highlighted)))
+            Padding::new(time::widget(timestamps, None, style_time(highlighted)))
                 .stretch(true)
                 .right(1),
         ),
@@ -110,8 +119,22 @@ pub fn msg_placeholder(
     .into()
 }
 
+pub fn gap(timestamps: &Timestamps) -> BoxedWidget {
+    HJoin::new(vec![
+        Segment::new(seen::widget(true, false)),
+        Segment::new(
+            Padding::new(time::widget(timestamps, None, style_time(false)))
+                .stretch(true)
+                .right(1),
+        ),
+        Segment::new(Text::new((GAP, style_placeholder()))),
+    ])
+    .into()
+}
+
 pub fn editor<M: ChatMsg>(
     widthdb: &mut WidthDb,
+    timestamps: &Timestamps,
     indent: usize,
     nick: &str,
     editor: &EditorState,
@@ -121,9 +144,9 @@ pub fn editor<M: ChatMsg>(
     let cursor_row = editor.cursor_row(widthdb);
 
     let widget = HJoin::new(vec![
-        Segment::new(seen::widget(true)),
+        Segment::new(seen::widget(true, false)),
         Segment::new(
-            Padding::new(time::widget(None, style_editor_highlight()))
+            Padding::new(time::widget(timestamps, None, style_editor_highlight()))
                 .stretch(true)
                 .right(1),
         ),
@@ -139,13 +162,18 @@ pub fn editor<M: ChatMsg>(
     (widget, cursor_row)
 }
 
-pub fn pseudo<M: ChatMsg>(indent: usize, nick: &str, editor: &EditorState) -> BoxedWidget {
+pub fn pseudo<M: ChatMsg>(
+    timestamps: &Timestamps,
+    indent: usize,
+    nick: &str,
+    editor: &EditorState,
+) -> BoxedWidget {
     let (nick, content) = M::edit(nick, &editor.text());
 
     HJoin::new(vec![
-        Segment::new(seen::widget(true)),
+        Segment::new(seen::widget(true, false)),
         Segment::new(
-            Padding::new(time::widget(None, style_pseudo_highlight()))
+            Padding::new(time::widget(timestamps, None, style_pseudo_highlight()))
                 .stretch(true)
                 .right(1),
         ),
@@ -6,19 +6,29 @@ use crate::ui::widgets::text::Text;
 use crate::ui::widgets::BoxedWidget;
 
 const UNSEEN: &str = "*";
+const MENTION: &str = "@";
 const WIDTH: u16 = 1;
 
 fn seen_style() -> ContentStyle {
     ContentStyle::default().black().on_green()
 }
 
-pub fn widget(seen: bool) -> BoxedWidget {
-    if seen {
-        Empty::new().width(WIDTH).into()
-    } else {
-        let style = seen_style();
-        Background::new(Text::new((UNSEEN, style)))
-            .style(style)
-            .into()
-    }
+fn mention_style() -> ContentStyle {
+    ContentStyle::default().black().on_yellow()
+}
+
+fn seen_mention_style() -> ContentStyle {
+    ContentStyle::default().yellow()
+}
+
+pub fn widget(seen: bool, mention: bool) -> BoxedWidget {
+    let (text, style) = match (seen, mention) {
+        (true, false) => return Empty::new().width(WIDTH).into(),
+        (true, true) => (MENTION, seen_mention_style()),
+        (false, false) => (UNSEEN, seen_style()),
+        (false, true) => (MENTION, mention_style()),
+    };
+    Background::new(Text::new((text, style)))
+        .style(style)
+        .into()
 }
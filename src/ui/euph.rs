@@ -1,8 +1,16 @@
 mod account;
 mod auth;
+mod bans;
+mod complete;
+mod delete_msg;
+mod edit_msg;
+mod emoji;
+mod ignores;
 mod inspect;
 mod links;
+mod moderate;
 mod nick;
 mod nick_list;
 mod popup;
 pub mod room;
+mod search;
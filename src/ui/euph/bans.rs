@@ -0,0 +1,165 @@
+use crossterm::style::{ContentStyle, Stylize};
+use euphoxide::api::UserId;
+use toss::terminal::Terminal;
+
+use crate::config::Keys;
+use crate::euph::Room;
+use crate::ui::input::{key, InputEvent, KeyBindingsList};
+use crate::ui::util;
+use crate::ui::widgets::editor::EditorState;
+use crate::ui::widgets::empty::Empty;
+use crate::ui::widgets::join::{HJoin, Segment, VJoin};
+use crate::ui::widgets::list::ListState;
+use crate::ui::widgets::popup::Popup;
+use crate::ui::widgets::resize::Resize;
+use crate::ui::widgets::text::Text;
+use crate::ui::widgets::BoxedWidget;
+
+/// Lifting bans, either ones issued during this session or ones entered by
+/// user id.
+///
+/// The euphoria API has no way of listing a room's bans, so only the bans we
+/// issued ourselves can be shown.
+pub struct BansState {
+    bans: Vec<UserId>,
+    list: ListState<UserId>,
+    /// Present while entering a user id to unban.
+    user_id: Option<EditorState>,
+}
+
+pub enum EventResult {
+    NotHandled,
+    Handled,
+    Close,
+    Unbanned(UserId),
+}
+
+impl BansState {
+    pub fn new(bans: Vec<UserId>) -> Self {
+        Self {
+            bans,
+            list: ListState::new(),
+            user_id: None,
+        }
+    }
+
+    fn user_id_widget(editor: &EditorState) -> BoxedWidget {
+        HJoin::new(vec![
+            Segment::new(Text::new(("User id:", ContentStyle::default().bold()))),
+            Segment::new(Empty::new().width(1)),
+            Segment::new(editor.widget()),
+        ])
+        .into()
+    }
+
+    fn bans_widget(&self, keys: &Keys) -> BoxedWidget {
+        let style_selected = ContentStyle::default().black().on_white();
+
+        let mut list = self.list.widget().focus(true);
+        if self.bans.is_empty() {
+            list.add_unsel(Text::new((
+                "No bans issued during this session",
+                ContentStyle::default().grey().italic(),
+            )));
+        }
+        for id in &self.bans {
+            list.add_sel(
+                id.clone(),
+                Text::new(id.0.as_str()),
+                Text::new((id.0.as_str(), style_selected)),
+            );
+        }
+
+        VJoin::new(vec![
+            Segment::new(list).expanding(true),
+            Segment::new(Empty::new().height(1)),
+            Segment::new(Text::new((
+                format!("Press {} to unban by user id", keys.room.unban_user_id),
+                ContentStyle::default().grey(),
+            ))),
+        ])
+        .into()
+    }
+
+    pub fn widget(&self, keys: &Keys) -> BoxedWidget {
+        let inner = match &self.user_id {
+            Some(editor) => Self::user_id_widget(editor),
+            None => self.bans_widget(keys),
+        };
+        Popup::new(Resize::new(inner).min_width(40))
+            .title("Bans")
+            .build()
+    }
+
+    pub fn list_key_bindings(&self, bindings: &mut KeyBindingsList, keys: &Keys) {
+        if self.user_id.is_some() {
+            bindings.binding("esc", "back to bans");
+            bindings.binding("enter", "unban user");
+            util::list_editor_key_bindings(bindings, |c| c != '\n');
+        } else {
+            bindings.binding("esc", "close bans popup");
+            util::list_list_key_bindings(bindings, keys);
+            bindings.empty();
+            bindings.binding("enter", "unban selected user");
+            bindings.action(&keys.room.unban_user_id, "unban by user id");
+        }
+    }
+
+    fn unban(&mut self, room: &Option<Room>, id: UserId) -> EventResult {
+        if let Some(room) = room {
+            let _ = room.unban(id.clone());
+        }
+        self.bans.retain(|ban| *ban != id);
+        EventResult::Unbanned(id)
+    }
+
+    pub fn handle_input_event(
+        &mut self,
+        terminal: &mut Terminal,
+        event: &InputEvent,
+        room: &Option<Room>,
+        keys: &Keys,
+    ) -> EventResult {
+        if let Some(editor) = &self.user_id {
+            return match event {
+                key!(Esc) => {
+                    self.user_id = None;
+                    EventResult::Handled
+                }
+                key!(Enter) => {
+                    let id = editor.text().trim().to_string();
+                    if id.is_empty() {
+                        return EventResult::Handled;
+                    }
+                    self.user_id = None;
+                    self.unban(room, UserId(id))
+                }
+                _ => {
+                    if util::handle_editor_input_event(editor, terminal, event, |c| c != '\n') {
+                        EventResult::Handled
+                    } else {
+                        EventResult::NotHandled
+                    }
+                }
+            };
+        }
+
+        if util::handle_list_input_event(&mut self.list, event, keys) {
+            return EventResult::Handled;
+        }
+
+        if keys.room.unban_user_id.matches(event) {
+            self.user_id = Some(EditorState::new());
+            return EventResult::Handled;
+        }
+
+        match event {
+            key!(Esc) => EventResult::Close,
+            key!(Enter) => match self.list.cursor() {
+                Some(id) => self.unban(room, id),
+                None => EventResult::Handled,
+            },
+            _ => EventResult::NotHandled,
+        }
+    }
+}
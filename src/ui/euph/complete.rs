@@ -0,0 +1,256 @@
+//! Tab completion of nicks, rooms and emoji in the compose editor.
+
+use std::ops::Range;
+
+use crossterm::style::{ContentStyle, Stylize};
+use toss::styled::Styled;
+use toss::widthdb::WidthDb;
+
+use crate::euph;
+use crate::ui::widgets::editor::EditorState;
+use crate::ui::widgets::popup::Popup;
+use crate::ui::widgets::text::Text;
+use crate::ui::widgets::BoxedWidget;
+
+/// Maximum amount of candidates visible in the popup at the same time.
+const VISIBLE_CANDIDATES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Nick,
+    Room,
+    Emoji,
+}
+
+fn emoji_char(ch: char) -> bool {
+    ch != ':' && !ch.is_whitespace()
+}
+
+/// Start of the word directly before the end of `before`, if it begins with
+/// `prefix` and otherwise only contains chars matching `word_char`.
+///
+/// Like the highlighting of messages, mentions and room links only start after
+/// non-alphanumeric chars if `needs_boundary` is set.
+fn word_start(
+    before: &str,
+    prefix: char,
+    word_char: impl Fn(char) -> bool,
+    needs_boundary: bool,
+) -> Option<usize> {
+    let mut start = None;
+    for (idx, char) in before.char_indices().rev() {
+        if char == prefix {
+            let boundary = before[..idx]
+                .chars()
+                .next_back()
+                .map(|c| !c.is_alphanumeric())
+                .unwrap_or(true);
+            if boundary || !needs_boundary {
+                start = Some(idx);
+            }
+        }
+        if !word_char(char) {
+            break;
+        }
+    }
+    start
+}
+
+/// The partially typed word directly before the editor's cursor, including its
+/// `@`, `&` or `:` prefix.
+fn partial_word(editor: &EditorState) -> Option<(Kind, Range<usize>)> {
+    let text = editor.text();
+    let cursor = editor.cursor();
+    let before = &text[..cursor];
+
+    let nick = word_start(before, '@', euph::nick_char, true).map(|i| (Kind::Nick, i));
+    let room = word_start(before, '&', euph::room_char, true).map(|i| (Kind::Room, i));
+    let emoji = word_start(before, ':', emoji_char, false)
+        .filter(|i| i + 1 < cursor) // Otherwise any closing colon would match
+        .map(|i| (Kind::Emoji, i));
+
+    // If multiple kinds of words match, the shortest one is the most likely.
+    [nick, room, emoji]
+        .into_iter()
+        .flatten()
+        .max_by_key(|(_, i)| *i)
+        .map(|(kind, i)| (kind, i..cursor))
+}
+
+pub struct Completion {
+    kind: Kind,
+    /// Where the candidates are inserted in the editor's text.
+    start: usize,
+    /// End of the currently inserted text.
+    end: usize,
+    /// The text that was typed before completion started.
+    original: String,
+    candidates: Vec<String>,
+    /// Index of the currently inserted candidate, `None` if the original text
+    /// is shown.
+    selected: Option<usize>,
+}
+
+impl Completion {
+    /// Start completing the word before the editor's cursor.
+    ///
+    /// Nicks are expected to be in the order they should be suggested in.
+    /// Returns `None` if there is no word to complete before the cursor.
+    pub fn new(editor: &EditorState, nicks: &[String], rooms: &[String]) -> Option<Self> {
+        let (kind, range) = partial_word(editor)?;
+        let text = editor.text();
+        let original = text[range.clone()].to_string();
+        let partial = &original[1..];
+
+        let candidates = match kind {
+            Kind::Nick => {
+                // Nicks may appear multiple times, but only their first
+                // occurrence is relevant.
+                let mut mentions = vec![];
+                for nick in nicks {
+                    let mention = format!("@{}", euph::mention(nick));
+                    if euph::mention_matches_nick(partial, nick) && !mentions.contains(&mention) {
+                        mentions.push(mention);
+                    }
+                }
+                mentions
+            }
+            Kind::Room => {
+                let partial = partial.to_lowercase();
+                let mut rooms = rooms
+                    .iter()
+                    .filter(|r| r.to_lowercase().starts_with(&partial))
+                    .map(|r| format!("&{r}"))
+                    .collect::<Vec<_>>();
+                rooms.sort_unstable();
+                rooms
+            }
+            Kind::Emoji => {
+                let mut names = euph::EMOJI
+                    .0
+                    .keys()
+                    .filter(|name| name.starts_with(partial))
+                    .map(|name| format!(":{name}:"))
+                    .collect::<Vec<_>>();
+                names.sort_unstable_by_key(|n| (n.len(), n.clone()));
+                names
+            }
+        };
+
+        Some(Self {
+            kind,
+            start: range.start,
+            end: range.end,
+            original,
+            candidates,
+            selected: None,
+        })
+    }
+
+    fn insert(&mut self, editor: &EditorState, widthdb: &mut WidthDb, selected: Option<usize>) {
+        let text = match selected {
+            Some(i) => &self.candidates[i],
+            None => &self.original,
+        };
+        // Completed nicks and rooms are followed by a space, like in most
+        // other clients. Emoji are often placed directly next to each other.
+        let text = match (selected, self.kind) {
+            (None, _) | (_, Kind::Emoji) => text.clone(),
+            _ => format!("{text} "),
+        };
+        editor.replace_range(widthdb, self.start..self.end, &text);
+        self.end = self.start + text.len();
+        self.selected = selected;
+    }
+
+    /// Insert the next candidate, cycling back to the original text after the
+    /// last one.
+    pub fn next(&mut self, editor: &EditorState, widthdb: &mut WidthDb) {
+        if self.candidates.is_empty() {
+            return;
+        }
+        let selected = match self.selected {
+            None => Some(0),
+            Some(i) if i + 1 < self.candidates.len() => Some(i + 1),
+            Some(_) => None,
+        };
+        self.insert(editor, widthdb, selected);
+    }
+
+    /// Insert the previous candidate, cycling back to the original text before
+    /// the first one.
+    pub fn prev(&mut self, editor: &EditorState, widthdb: &mut WidthDb) {
+        if self.candidates.is_empty() {
+            return;
+        }
+        let selected = match self.selected {
+            None => Some(self.candidates.len() - 1),
+            Some(0) => None,
+            Some(i) => Some(i - 1),
+        };
+        self.insert(editor, widthdb, selected);
+    }
+
+    /// Restore the text that was typed before completion started.
+    pub fn abort(&mut self, editor: &EditorState, widthdb: &mut WidthDb) {
+        self.insert(editor, widthdb, None);
+    }
+
+    fn styled_candidate(&self, candidate: &str) -> Styled {
+        match self.kind {
+            Kind::Nick => Styled::new_plain("@").and_then(euph::style_nick_exact(
+                &candidate[1..],
+                ContentStyle::default(),
+            )),
+            Kind::Room => Styled::new(candidate, ContentStyle::default().bold().blue()),
+            Kind::Emoji => {
+                let name = &candidate[1..candidate.len() - 1];
+                match euph::EMOJI.get(name) {
+                    Some(Some(emoji)) => Styled::new_plain(format!("{emoji} "))
+                        .then(candidate, ContentStyle::default().magenta()),
+                    _ => Styled::new(candidate, ContentStyle::default().magenta()),
+                }
+            }
+        }
+    }
+
+    pub fn widget(&self) -> BoxedWidget {
+        let selected_style = ContentStyle::default().black().on_white();
+
+        if self.candidates.is_empty() {
+            let text = ("No matches", ContentStyle::default().grey().italic());
+            return Popup::new(Text::new(text)).title("Completion").build();
+        }
+
+        // Scroll the visible candidates so the selected one is always visible.
+        let selected = self.selected.unwrap_or(0);
+        let first = selected.saturating_sub(VISIBLE_CANDIDATES - 1);
+        let last = (first + VISIBLE_CANDIDATES).min(self.candidates.len());
+
+        let mut text = Styled::default();
+        if first > 0 {
+            text = text.then("...\n", ContentStyle::default().grey());
+        }
+        for (i, candidate) in self.candidates[first..last].iter().enumerate() {
+            if i > 0 {
+                text = text.then_plain("\n");
+            }
+            if self.selected == Some(first + i) {
+                text = text.then(candidate, selected_style);
+            } else {
+                text = text.and_then(self.styled_candidate(candidate));
+            }
+        }
+        if last < self.candidates.len() {
+            text = text.then("\n...", ContentStyle::default().grey());
+        }
+
+        Popup::new(Text::new(text))
+            .title(format!(
+                "Completion {}/{}",
+                selected + 1,
+                self.candidates.len()
+            ))
+            .build()
+    }
+}
@@ -0,0 +1,48 @@
+use crossterm::style::{ContentStyle, Stylize};
+use euphoxide::api::Message;
+use toss::styled::Styled;
+
+use crate::euph::{self, Room};
+use crate::ui::input::{key, InputEvent, KeyBindingsList};
+use crate::ui::widgets::popup::Popup;
+use crate::ui::widgets::resize::Resize;
+use crate::ui::widgets::text::Text;
+use crate::ui::widgets::BoxedWidget;
+
+pub fn widget(msg: &Message) -> BoxedWidget {
+    let warn_style = ContentStyle::default().bold().red();
+    let text = Styled::new_plain("Are you sure you want to delete this message by ")
+        .and_then(euph::style_nick(&msg.sender.name, ContentStyle::default()))
+        .then_plain("?\n\n")
+        .then(msg.content.trim(), ContentStyle::default().italic().grey())
+        .then_plain("\n\n")
+        .then_plain("This will delete the message for everyone in the room.\n\n")
+        .then_plain("To confirm the deletion, press enter.");
+    Popup::new(Resize::new(Text::new(text).wrap(true)).max_width(54))
+        .title(("Delete message", warn_style))
+        .border(warn_style)
+        .build()
+}
+
+pub fn list_key_bindings(bindings: &mut KeyBindingsList) {
+    bindings.binding("esc", "abort");
+    bindings.binding("enter", "delete message");
+}
+
+pub enum EventResult {
+    NotHandled,
+    ResetState,
+}
+
+pub fn handle_input_event(event: &InputEvent, room: &Option<Room>, msg: &Message) -> EventResult {
+    match event {
+        key!(Esc) => EventResult::ResetState,
+        key!(Enter) => {
+            if let Some(room) = &room {
+                let _ = room.delete_message(msg);
+            }
+            EventResult::ResetState
+        }
+        _ => EventResult::NotHandled,
+    }
+}
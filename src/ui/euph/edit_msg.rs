@@ -0,0 +1,67 @@
+use crossterm::style::ContentStyle;
+use euphoxide::api::Message;
+use toss::terminal::Terminal;
+
+use crate::euph::{self, Room};
+use crate::ui::input::{key, InputEvent, KeyBindingsList};
+use crate::ui::util;
+use crate::ui::widgets::editor::EditorState;
+use crate::ui::widgets::padding::Padding;
+use crate::ui::widgets::popup::Popup;
+use crate::ui::widgets::BoxedWidget;
+
+pub fn new(msg: &Message) -> EditorState {
+    EditorState::with_initial_text(msg.content.clone())
+}
+
+pub fn widget(msg: &Message, editor: &EditorState) -> BoxedWidget {
+    let editor = editor.widget();
+    Popup::new(Padding::new(editor).left(1))
+        .title(euph::style_nick(&msg.sender.name, ContentStyle::default()).then_plain("'s message"))
+        .inner_padding(false)
+        .build()
+}
+
+fn content_char(_c: char) -> bool {
+    true
+}
+
+pub fn list_key_bindings(bindings: &mut KeyBindingsList) {
+    bindings.binding("esc", "abort");
+    bindings.binding("enter", "edit message");
+    util::list_editor_key_bindings(bindings, content_char);
+}
+
+pub enum EventResult {
+    NotHandled,
+    Handled,
+    ResetState,
+}
+
+pub fn handle_input_event(
+    terminal: &mut Terminal,
+    event: &InputEvent,
+    room: &Option<Room>,
+    msg: &Message,
+    editor: &EditorState,
+) -> EventResult {
+    match event {
+        key!(Esc) => EventResult::ResetState,
+        key!(Enter) => {
+            let content = editor.text();
+            if let Some(room) = &room {
+                if !content.trim().is_empty() && content != msg.content {
+                    let _ = room.edit_message(msg, content);
+                }
+            }
+            EventResult::ResetState
+        }
+        _ => {
+            if util::handle_editor_input_event(editor, terminal, event, content_char) {
+                EventResult::Handled
+            } else {
+                EventResult::NotHandled
+            }
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use crossterm::style::{ContentStyle, Stylize};
+use toss::styled::Styled;
+use toss::terminal::Terminal;
+
+use crate::config::Keys;
+use crate::euph;
+use crate::ui::input::{key, InputEvent, KeyBindingsList};
+use crate::ui::util;
+use crate::ui::widgets::editor::EditorState;
+use crate::ui::widgets::empty::Empty;
+use crate::ui::widgets::join::{HJoin, Segment, VJoin};
+use crate::ui::widgets::list::ListState;
+use crate::ui::widgets::popup::Popup;
+use crate::ui::widgets::resize::Resize;
+use crate::ui::widgets::rules::HRule;
+use crate::ui::widgets::text::Text;
+use crate::ui::widgets::BoxedWidget;
+
+pub struct EmojiPickerState {
+    filter: EditorState,
+    /// The filter the current matches belong to.
+    filtered: String,
+    /// Names and glyphs of all emoji matching the filter.
+    matches: Vec<(String, Option<String>)>,
+    list: ListState<String>,
+}
+
+pub enum EventResult {
+    NotHandled,
+    Handled,
+    Close,
+    /// Insert the code of an emoji into the message editor.
+    Insert(String),
+}
+
+fn filter_char(c: char) -> bool {
+    c != '\n'
+}
+
+fn matching_emoji(filter: &str) -> Vec<(String, Option<String>)> {
+    let filter = filter.trim().to_lowercase();
+    let mut matches = euph::EMOJI
+        .0
+        .iter()
+        .filter(|(name, _)| name.contains(&filter))
+        .map(|(name, glyph)| (name.clone(), glyph.clone()))
+        .collect::<Vec<_>>();
+
+    // Emoji starting with the filter are most likely what we're looking for.
+    matches.sort_unstable_by(|(a, _), (b, _)| {
+        let a_key = (!a.starts_with(&filter), a.len(), a);
+        let b_key = (!b.starts_with(&filter), b.len(), b);
+        a_key.cmp(&b_key)
+    });
+    matches
+}
+
+impl EmojiPickerState {
+    pub fn new() -> Self {
+        Self {
+            filter: EditorState::new(),
+            filtered: String::new(),
+            matches: matching_emoji(""),
+            list: ListState::new(),
+        }
+    }
+
+    pub fn widget(&self) -> BoxedWidget {
+        let bold = ContentStyle::default().bold();
+        let style_selected = ContentStyle::default().black().on_white();
+
+        let mut list = self.list.widget().focus(true);
+        if self.matches.is_empty() {
+            list.add_unsel(Text::new((
+                "No emoji found",
+                ContentStyle::default().grey().italic(),
+            )));
+        }
+        for (name, glyph) in &self.matches {
+            let code = format!(":{name}:");
+            // Not every emoji has a unicode equivalent.
+            let glyph = glyph.as_deref().unwrap_or(" ");
+            let normal = Styled::new_plain(glyph)
+                .then_plain(" ")
+                .then(&code, ContentStyle::default().magenta());
+            let selected = Styled::new(format!("{glyph} {code}"), style_selected);
+            list.add_sel(name.clone(), Text::new(normal), Text::new(selected));
+        }
+
+        let inner = VJoin::new(vec![
+            Segment::new(HJoin::new(vec![
+                Segment::new(Text::new(("Filter:", bold))),
+                Segment::new(Empty::new().width(1)),
+                Segment::new(self.filter.widget()).expanding(true),
+            ])),
+            Segment::new(HRule),
+            Segment::new(list).expanding(true),
+        ]);
+
+        Popup::new(Resize::new(inner).min_width(40).max_height(20))
+            .title("Emoji")
+            .build()
+    }
+
+    pub fn list_key_bindings(&self, bindings: &mut KeyBindingsList, keys: &Keys) {
+        bindings.binding("esc", "close emoji picker");
+        bindings.binding("enter", "insert selected emoji");
+        util::list_list_key_bindings(bindings, keys);
+        bindings.empty();
+        util::list_editor_key_bindings(bindings, filter_char);
+    }
+
+    pub fn handle_input_event(
+        &mut self,
+        terminal: &mut Terminal,
+        event: &InputEvent,
+        keys: &Keys,
+    ) -> EventResult {
+        match event {
+            key!(Esc) => return EventResult::Close,
+            key!(Enter) => {
+                return match self.list.cursor() {
+                    Some(name) => EventResult::Insert(format!(":{name}:")),
+                    None => EventResult::Handled,
+                };
+            }
+            // Typed text always goes to the filter, even if it is also bound
+            // to a list action like "k" or "j".
+            key!(Char _) | InputEvent::Paste(_) => {
+                if !util::handle_editor_input_event(&self.filter, terminal, event, filter_char) {
+                    return EventResult::NotHandled;
+                }
+            }
+            _ => {
+                if util::handle_list_input_event(&mut self.list, event, keys) {
+                    return EventResult::Handled;
+                }
+                if !util::handle_editor_input_event(&self.filter, terminal, event, filter_char) {
+                    return EventResult::NotHandled;
+                }
+            }
+        }
+
+        let filter = self.filter.text();
+        if filter != self.filtered {
+            self.matches = matching_emoji(&filter);
+            self.filtered = filter;
+            self.list.move_cursor_to_top();
+        }
+
+        EventResult::Handled
+    }
+}
@@ -0,0 +1,198 @@
+use crossterm::style::{ContentStyle, Stylize};
+use euphoxide::api::UserId;
+use toss::styled::Styled;
+use toss::terminal::Terminal;
+
+use crate::config::Keys;
+use crate::euph::{self, Ignore};
+use crate::ui::input::{key, InputEvent, KeyBindingsList};
+use crate::ui::util;
+use crate::ui::widgets::editor::EditorState;
+use crate::ui::widgets::empty::Empty;
+use crate::ui::widgets::join::{HJoin, Segment, VJoin};
+use crate::ui::widgets::list::ListState;
+use crate::ui::widgets::popup::Popup;
+use crate::ui::widgets::resize::Resize;
+use crate::ui::widgets::text::Text;
+use crate::ui::widgets::BoxedWidget;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Adding {
+    Nick,
+    UserId,
+}
+
+/// Viewing and editing the ignore list, which is shared by all rooms.
+pub struct IgnoresState {
+    ignores: Vec<Ignore>,
+    list: ListState<Ignore>,
+    /// Present while entering a new nick pattern or user id to ignore.
+    adding: Option<(Adding, EditorState)>,
+}
+
+pub enum EventResult {
+    NotHandled,
+    Handled,
+    Close,
+    Added(Ignore),
+    Removed(Ignore),
+}
+
+fn styled_ignore(ignore: &Ignore) -> Styled {
+    let grey = ContentStyle::default().grey();
+    match ignore {
+        Ignore::UserId(id) => Styled::new("user id ", grey).then_plain(&id.0),
+        Ignore::Nick(pattern) => Styled::new("nick    ", grey)
+            .and_then(euph::style_nick_exact(pattern, ContentStyle::default())),
+    }
+}
+
+impl IgnoresState {
+    pub fn new(ignores: Vec<Ignore>) -> Self {
+        Self {
+            ignores,
+            list: ListState::new(),
+            adding: None,
+        }
+    }
+
+    fn adding_widget(adding: Adding, editor: &EditorState) -> BoxedWidget {
+        let (label, hint) = match adding {
+            Adding::Nick => ("Nick:", "use * to match any amount of characters"),
+            Adding::UserId => ("User id:", "e.g. agent:0123456789abcdef"),
+        };
+        VJoin::new(vec![
+            Segment::new(HJoin::new(vec![
+                Segment::new(Text::new((label, ContentStyle::default().bold()))),
+                Segment::new(Empty::new().width(1)),
+                Segment::new(editor.widget()),
+            ])),
+            Segment::new(Text::new((hint, ContentStyle::default().grey()))),
+        ])
+        .into()
+    }
+
+    fn ignores_widget(&self, keys: &Keys) -> BoxedWidget {
+        let style_selected = ContentStyle::default().black().on_white();
+
+        let mut list = self.list.widget().focus(true);
+        if self.ignores.is_empty() {
+            list.add_unsel(Text::new((
+                "Nobody is ignored",
+                ContentStyle::default().grey().italic(),
+            )));
+        }
+        for ignore in &self.ignores {
+            let text = match ignore {
+                Ignore::UserId(id) => format!("user id {}", id.0),
+                Ignore::Nick(pattern) => format!("nick    {pattern}"),
+            };
+            list.add_sel(
+                ignore.clone(),
+                Text::new(styled_ignore(ignore)),
+                Text::new((text, style_selected)),
+            );
+        }
+
+        VJoin::new(vec![
+            Segment::new(list).expanding(true),
+            Segment::new(Empty::new().height(1)),
+            Segment::new(Text::new((
+                format!(
+                    "Press {} to ignore a nick, {} to ignore a user id",
+                    keys.room.ignore_nick, keys.room.ignore_user_id
+                ),
+                ContentStyle::default().grey(),
+            ))),
+        ])
+        .into()
+    }
+
+    pub fn widget(&self, keys: &Keys) -> BoxedWidget {
+        let inner = match &self.adding {
+            Some((adding, editor)) => Self::adding_widget(*adding, editor),
+            None => self.ignores_widget(keys),
+        };
+        Popup::new(Resize::new(inner).min_width(40))
+            .title("Ignored users")
+            .build()
+    }
+
+    pub fn list_key_bindings(&self, bindings: &mut KeyBindingsList, keys: &Keys) {
+        if self.adding.is_some() {
+            bindings.binding("esc", "back to ignore list");
+            bindings.binding("enter", "ignore");
+            util::list_editor_key_bindings(bindings, |c| c != '\n');
+        } else {
+            bindings.binding("esc", "close ignore list");
+            util::list_list_key_bindings(bindings, keys);
+            bindings.empty();
+            bindings.binding("enter, delete", "stop ignoring selected entry");
+            bindings.action(&keys.room.ignore_nick, "ignore nick pattern");
+            bindings.action(&keys.room.ignore_user_id, "ignore user id");
+        }
+    }
+
+    pub fn handle_input_event(
+        &mut self,
+        terminal: &mut Terminal,
+        event: &InputEvent,
+        keys: &Keys,
+    ) -> EventResult {
+        if let Some((adding, editor)) = &self.adding {
+            return match event {
+                key!(Esc) => {
+                    self.adding = None;
+                    EventResult::Handled
+                }
+                key!(Enter) => {
+                    let text = editor.text().trim().to_string();
+                    if text.is_empty() {
+                        return EventResult::Handled;
+                    }
+                    let ignore = match adding {
+                        Adding::Nick => Ignore::Nick(text),
+                        Adding::UserId => Ignore::UserId(UserId(text)),
+                    };
+                    self.adding = None;
+                    if !self.ignores.contains(&ignore) {
+                        self.ignores.push(ignore.clone());
+                    }
+                    EventResult::Added(ignore)
+                }
+                _ => {
+                    if util::handle_editor_input_event(editor, terminal, event, |c| c != '\n') {
+                        EventResult::Handled
+                    } else {
+                        EventResult::NotHandled
+                    }
+                }
+            };
+        }
+
+        if util::handle_list_input_event(&mut self.list, event, keys) {
+            return EventResult::Handled;
+        }
+
+        if keys.room.ignore_nick.matches(event) {
+            self.adding = Some((Adding::Nick, EditorState::new()));
+            return EventResult::Handled;
+        }
+        if keys.room.ignore_user_id.matches(event) {
+            self.adding = Some((Adding::UserId, EditorState::new()));
+            return EventResult::Handled;
+        }
+
+        match event {
+            key!(Esc) => EventResult::Close,
+            key!(Enter) | key!(Delete) => match self.list.cursor() {
+                Some(ignore) => {
+                    self.ignores.retain(|i| *i != ignore);
+                    EventResult::Removed(ignore)
+                }
+                None => EventResult::Handled,
+            },
+            _ => EventResult::NotHandled,
+        }
+    }
+}
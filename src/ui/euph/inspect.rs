@@ -1,13 +1,20 @@
 use crossterm::style::{ContentStyle, Stylize};
-use euphoxide::api::{Message, NickEvent, SessionView};
+use euphoxide::api::{Message, NickEvent, SessionView, Time};
 use euphoxide::conn::SessionInfo;
+use time::format_description::FormatItem;
+use time::macros::format_description;
 use toss::styled::Styled;
 
+use crate::config::Timestamps;
 use crate::ui::input::{key, InputEvent, KeyBindingsList};
 use crate::ui::widgets::popup::Popup;
 use crate::ui::widgets::text::Text;
 use crate::ui::widgets::BoxedWidget;
 
+const TIME_FORMAT: &[FormatItem<'_>] = format_description!(
+    "[year]-[month]-[day] [hour]:[minute]:[second] [offset_hour sign:mandatory]:[offset_minute]"
+);
+
 macro_rules! line {
     ( $text:ident, $name:expr, $val:expr ) => {
         $text = $text
@@ -73,14 +80,16 @@ fn nick_event_lines(mut text: Styled, event: &NickEvent) -> Styled {
     text
 }
 
-fn message_lines(mut text: Styled, msg: &Message) -> Styled {
+fn message_lines(mut text: Styled, msg: &Message, timestamps: &Timestamps) -> Styled {
+    let format_time = |time: Time| timestamps.format(time.0, TIME_FORMAT);
+
     line!(text, "id", msg.id.0);
     line!(text, "parent", msg.parent.map(|p| p.0), optional);
     line!(text, "previous_edit_id", msg.previous_edit_id, optional);
-    line!(text, "time", msg.time.0);
+    line!(text, "time", format_time(msg.time));
     line!(text, "encryption_key_id", &msg.encryption_key_id, optional);
-    line!(text, "edited", msg.edited.map(|t| t.0), optional);
-    line!(text, "deleted", msg.deleted.map(|t| t.0), optional);
+    line!(text, "edited", msg.edited.map(format_time), optional);
+    line!(text, "deleted", msg.deleted.map(format_time), optional);
     line!(text, "truncated", msg.truncated, yes or no);
 
     text
@@ -103,12 +112,32 @@ pub fn session_widget(session: &SessionInfo) -> BoxedWidget {
     Popup::new(Text::new(text)).title("Inspect session").build()
 }
 
-pub fn message_widget(msg: &Message) -> BoxedWidget {
+fn history_lines(
+    mut text: Styled,
+    history: &[(Option<Time>, String)],
+    timestamps: &Timestamps,
+) -> Styled {
+    for (edited, content) in history {
+        let name = match edited {
+            Some(time) => format!("edited {}", timestamps.format(time.0, TIME_FORMAT)),
+            None => "original".to_string(),
+        };
+        line!(text, name, content, debug);
+    }
+
+    text
+}
+
+pub fn message_widget(
+    msg: &Message,
+    history: &[(Option<Time>, String)],
+    timestamps: &Timestamps,
+) -> BoxedWidget {
     let heading_style = ContentStyle::default().bold();
 
     let mut text = Styled::new("Message", heading_style).then_plain("\n");
 
-    text = message_lines(text, msg);
+    text = message_lines(text, msg, timestamps);
 
     text = text
         .then_plain("\n")
@@ -117,6 +146,15 @@ pub fn message_widget(msg: &Message) -> BoxedWidget {
 
     text = session_view_lines(text, &msg.sender);
 
+    if !history.is_empty() {
+        text = text
+            .then_plain("\n")
+            .then("Previous versions", heading_style)
+            .then_plain("\n");
+
+        text = history_lines(text, history, timestamps);
+    }
+
     Popup::new(Text::new(text)).title("Inspect message").build()
 }
 
@@ -4,7 +4,9 @@ use crossterm::style::{ContentStyle, Stylize};
 use linkify::{LinkFinder, LinkKind};
 use toss::styled::Styled;
 
+use crate::config::Keys;
 use crate::ui::input::{key, InputEvent, KeyBindingsList};
+use crate::ui::util;
 use crate::ui::widgets::list::ListState;
 use crate::ui::widgets::popup::Popup;
 use crate::ui::widgets::text::Text;
@@ -100,39 +102,33 @@ impl LinksState {
         }
     }
 
-    pub fn list_key_bindings(&self, bindings: &mut KeyBindingsList) {
+    pub fn list_key_bindings(&self, bindings: &mut KeyBindingsList, keys: &Keys) {
         bindings.binding("esc", "close links popup");
-        bindings.binding("j/k, ↓/↑", "move cursor up/down");
-        bindings.binding("g, home", "move cursor to top");
-        bindings.binding("G, end", "move cursor to bottom");
-        bindings.binding("ctrl+y/e", "scroll up/down");
+        util::list_list_key_bindings(bindings, keys);
         bindings.empty();
         bindings.binding("enter", "open selected link");
         bindings.binding("1,2,...", "open link by position");
     }
 
-    pub fn handle_input_event(&mut self, event: &InputEvent) -> EventResult {
+    pub fn handle_input_event(&mut self, event: &InputEvent, keys: &Keys) -> EventResult {
+        if util::handle_list_input_event(&mut self.list, event, keys) {
+            return EventResult::Handled;
+        }
+
         match event {
-            key!(Esc) => return EventResult::Close,
-            key!('k') | key!(Up) => self.list.move_cursor_up(),
-            key!('j') | key!(Down) => self.list.move_cursor_down(),
-            key!('g') | key!(Home) => self.list.move_cursor_to_top(),
-            key!('G') | key!(End) => self.list.move_cursor_to_bottom(),
-            key!(Ctrl + 'y') => self.list.scroll_up(1),
-            key!(Ctrl + 'e') => self.list.scroll_down(1),
-            key!(Enter) => return self.open_link(),
-            key!('1') => return self.open_link_by_id(0),
-            key!('2') => return self.open_link_by_id(1),
-            key!('3') => return self.open_link_by_id(2),
-            key!('4') => return self.open_link_by_id(3),
-            key!('5') => return self.open_link_by_id(4),
-            key!('6') => return self.open_link_by_id(5),
-            key!('7') => return self.open_link_by_id(6),
-            key!('8') => return self.open_link_by_id(7),
-            key!('9') => return self.open_link_by_id(8),
-            key!('0') => return self.open_link_by_id(9),
-            _ => return EventResult::NotHandled,
+            key!(Esc) => EventResult::Close,
+            key!(Enter) => self.open_link(),
+            key!('1') => self.open_link_by_id(0),
+            key!('2') => self.open_link_by_id(1),
+            key!('3') => self.open_link_by_id(2),
+            key!('4') => self.open_link_by_id(3),
+            key!('5') => self.open_link_by_id(4),
+            key!('6') => self.open_link_by_id(5),
+            key!('7') => self.open_link_by_id(6),
+            key!('8') => self.open_link_by_id(7),
+            key!('9') => self.open_link_by_id(8),
+            key!('0') => self.open_link_by_id(9),
+            _ => EventResult::NotHandled,
         }
-        EventResult::Handled
     }
 }
@@ -0,0 +1,232 @@
+use crossterm::style::{ContentStyle, Stylize};
+use euphoxide::api::{AccountId, UserId};
+use toss::styled::Styled;
+use toss::terminal::Terminal;
+
+use crate::config::Keys;
+use crate::duration;
+use crate::euph::{self, Room};
+use crate::ui::input::{key, InputEvent, KeyBindingsList};
+use crate::ui::util;
+use crate::ui::widgets::editor::EditorState;
+use crate::ui::widgets::empty::Empty;
+use crate::ui::widgets::join::{HJoin, Segment, VJoin};
+use crate::ui::widgets::list::ListState;
+use crate::ui::widgets::popup::Popup;
+use crate::ui::widgets::resize::Resize;
+use crate::ui::widgets::text::Text;
+use crate::ui::widgets::BoxedWidget;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Ban,
+    GrantAccess,
+    RevokeAccess,
+    GrantManager,
+    RevokeManager,
+}
+
+impl Action {
+    fn description(self) -> &'static str {
+        match self {
+            Self::Ban => "Ban from room",
+            Self::GrantAccess => "Grant access to private room",
+            Self::RevokeAccess => "Revoke access to private room",
+            Self::GrantManager => "Grant manager permissions",
+            Self::RevokeManager => "Revoke manager permissions",
+        }
+    }
+}
+
+/// The account behind a user id, if the user is logged in.
+fn account_id(id: &UserId) -> Option<AccountId> {
+    let snowflake = id.0.strip_prefix("account:")?.parse().ok()?;
+    Some(AccountId(snowflake))
+}
+
+/// Parse a ban duration like `90s`, `30m`, `12h`, `7d` or `2w`.
+///
+/// Returns `Some(None)` for an empty duration, meaning the ban is permanent.
+fn parse_duration(text: &str) -> Option<Option<i32>> {
+    if text.trim().is_empty() {
+        return Some(None);
+    }
+
+    let seconds = i32::try_from(duration::parse_seconds(text)?).ok()?;
+    if seconds > 0 {
+        Some(Some(seconds))
+    } else {
+        None
+    }
+}
+
+pub struct ModerateState {
+    user_id: UserId,
+    nick: String,
+    actions: Vec<Action>,
+    list: ListState<Action>,
+    /// Present while entering the duration of a ban.
+    ban_duration: Option<EditorState>,
+}
+
+pub enum EventResult {
+    NotHandled,
+    Handled,
+    ResetState,
+    Banned(UserId),
+}
+
+impl ModerateState {
+    pub fn new(user_id: UserId, nick: String) -> Self {
+        let mut actions = vec![Action::Ban];
+        if account_id(&user_id).is_some() {
+            actions.extend([
+                Action::GrantAccess,
+                Action::RevokeAccess,
+                Action::GrantManager,
+                Action::RevokeManager,
+            ]);
+        }
+
+        Self {
+            user_id,
+            nick,
+            actions,
+            list: ListState::new(),
+            ban_duration: None,
+        }
+    }
+
+    fn title(&self) -> Styled {
+        Styled::new_plain("Moderate ")
+            .and_then(euph::style_nick(&self.nick, ContentStyle::default()))
+    }
+
+    fn ban_duration_widget(&self, editor: &EditorState) -> BoxedWidget {
+        let bold = ContentStyle::default().bold();
+        let valid = parse_duration(&editor.text()).is_some();
+        let hint = if valid {
+            (
+                "e.g. 30m, 12h, 7d or empty for a permanent ban",
+                ContentStyle::default().grey(),
+            )
+        } else {
+            ("invalid duration", ContentStyle::default().red())
+        };
+        VJoin::new(vec![
+            Segment::new(HJoin::new(vec![
+                Segment::new(Text::new(("Duration:", bold))),
+                Segment::new(Empty::new().width(1)),
+                Segment::new(editor.widget()),
+            ])),
+            Segment::new(Text::new(hint)),
+        ])
+        .into()
+    }
+
+    fn actions_widget(&self) -> BoxedWidget {
+        let style_selected = ContentStyle::default().black().on_white();
+
+        let mut list = self.list.widget().focus(true);
+        for action in &self.actions {
+            let description = action.description();
+            list.add_sel(
+                *action,
+                Text::new(description),
+                Text::new((description, style_selected)),
+            );
+        }
+        if account_id(&self.user_id).is_none() {
+            list.add_unsel(Text::new((
+                "Not logged in, no account actions available",
+                ContentStyle::default().grey().italic(),
+            )));
+        }
+
+        list.into()
+    }
+
+    pub fn widget(&self) -> BoxedWidget {
+        let inner = match &self.ban_duration {
+            Some(editor) => self.ban_duration_widget(editor),
+            None => self.actions_widget(),
+        };
+        Popup::new(Resize::new(inner).min_width(40))
+            .title(self.title())
+            .build()
+    }
+
+    pub fn list_key_bindings(&self, bindings: &mut KeyBindingsList, keys: &Keys) {
+        if self.ban_duration.is_some() {
+            bindings.binding("esc", "back to actions");
+            bindings.binding("enter", "ban user");
+            util::list_editor_key_bindings(bindings, |c| c != '\n');
+        } else {
+            bindings.binding("esc", "close moderation popup");
+            util::list_list_key_bindings(bindings, keys);
+            bindings.empty();
+            bindings.binding("enter", "perform selected action");
+        }
+    }
+
+    fn perform(&mut self, room: &Option<Room>, action: Action) -> EventResult {
+        let _ = match (action, room, account_id(&self.user_id)) {
+            (Action::Ban, _, _) => {
+                self.ban_duration = Some(EditorState::new());
+                return EventResult::Handled;
+            }
+            (Action::GrantAccess, Some(room), Some(id)) => room.grant_access(id),
+            (Action::RevokeAccess, Some(room), Some(id)) => room.revoke_access(id),
+            (Action::GrantManager, Some(room), Some(id)) => room.grant_manager(id),
+            (Action::RevokeManager, Some(room), Some(id)) => room.revoke_manager(id),
+            _ => Ok(()),
+        };
+        EventResult::ResetState
+    }
+
+    pub fn handle_input_event(
+        &mut self,
+        terminal: &mut Terminal,
+        event: &InputEvent,
+        room: &Option<Room>,
+        keys: &Keys,
+    ) -> EventResult {
+        if let Some(editor) = &self.ban_duration {
+            return match event {
+                key!(Esc) => {
+                    self.ban_duration = None;
+                    EventResult::Handled
+                }
+                key!(Enter) => match parse_duration(&editor.text()) {
+                    Some(seconds) => {
+                        if let Some(room) = room {
+                            let _ = room.ban(self.user_id.clone(), seconds);
+                        }
+                        EventResult::Banned(self.user_id.clone())
+                    }
+                    None => EventResult::Handled,
+                },
+                _ => {
+                    if util::handle_editor_input_event(editor, terminal, event, |c| c != '\n') {
+                        EventResult::Handled
+                    } else {
+                        EventResult::NotHandled
+                    }
+                }
+            };
+        }
+
+        if util::handle_list_input_event(&mut self.list, event, keys) {
+            return EventResult::Handled;
+        }
+
+        match event {
+            key!(Esc) => EventResult::ResetState,
+            key!(Enter) => match self.list.cursor() {
+                Some(action) => self.perform(room, action),
+                None => EventResult::Handled,
+            },
+            _ => EventResult::NotHandled,
+        }
+    }
+}
@@ -1,13 +1,22 @@
 use crossterm::style::{ContentStyle, Stylize};
 use toss::styled::Styled;
 
+use crate::euph;
 use crate::ui::widgets::float::Float;
 use crate::ui::widgets::popup::Popup;
 use crate::ui::widgets::text::Text;
 use crate::ui::widgets::BoxedWidget;
 
 pub enum RoomPopup {
-    Error { description: String, reason: String },
+    Error {
+        description: String,
+        reason: String,
+    },
+    Pm {
+        from_nick: String,
+        from_room: String,
+        room: String,
+    },
 }
 
 impl RoomPopup {
@@ -24,12 +33,30 @@ impl RoomPopup {
             .build()
     }
 
+    fn pm_widget(from_nick: &str, from_room: &str) -> BoxedWidget {
+        let room_style = ContentStyle::default().bold().blue();
+        let text = euph::style_nick(from_nick, ContentStyle::default())
+            .then_plain(" wants to talk to you in private from ")
+            .then(format!("&{from_room}"), room_style)
+            .then_plain(".\n\n")
+            .then("enter", ContentStyle::default().bold())
+            .then_plain(" to join, ")
+            .then("esc", ContentStyle::default().bold())
+            .then_plain(" to ignore");
+        Popup::new(Text::new(text)).title("Private message").build()
+    }
+
     pub fn widget(&self) -> BoxedWidget {
         let widget = match self {
             Self::Error {
                 description,
                 reason,
             } => Self::server_error_widget(description, reason),
+            Self::Pm {
+                from_nick,
+                from_room,
+                ..
+            } => Self::pm_widget(from_nick, from_room),
         };
 
         Float::new(widget).horizontal(0.5).vertical(0.5).into()
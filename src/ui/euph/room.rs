@@ -2,7 +2,7 @@ use std::collections::VecDeque;
 use std::sync::Arc;
 
 use crossterm::style::{ContentStyle, Stylize};
-use euphoxide::api::{Data, Message, MessageId, PacketType, SessionId};
+use euphoxide::api::{Data, Message, MessageId, PacketType, SessionId, Time, UserId};
 use euphoxide::bot::instance::{Event, ServerConfig};
 use euphoxide::conn::{self, Joined, Joining, SessionInfo};
 use parking_lot::FairMutex;
@@ -11,7 +11,7 @@ use tokio::sync::{mpsc, oneshot};
 use toss::styled::Styled;
 use toss::terminal::Terminal;
 
-use crate::config;
+use crate::config::{self, Keys, Timestamps};
 use crate::euph;
 use crate::macros::logging_unwrap;
 use crate::ui::chat::{ChatState, Reaction};
@@ -28,9 +28,19 @@ use crate::ui::{util, UiEvent};
 use crate::vault::EuphRoomVault;
 
 use super::account::{self, AccountUiState};
+use super::bans::{self, BansState};
+use super::complete::Completion;
+use super::emoji::{self, EmojiPickerState};
+use super::ignores::{self, IgnoresState};
 use super::links::{self, LinksState};
+use super::moderate::{self, ModerateState};
 use super::popup::RoomPopup;
-use super::{auth, inspect, nick, nick_list};
+use super::search::{self, SearchState};
+use super::{auth, delete_msg, edit_msg, inspect, nick, nick_list};
+
+/// How many of the nicks that spoke most recently are suggested when
+/// completing mentions.
+const RECENT_NICKS: usize = 100;
 
 #[derive(Debug, PartialEq, Eq)]
 enum Focus {
@@ -43,13 +53,23 @@ enum State {
     Normal,
     Auth(EditorState),
     Nick(EditorState),
+    EditMessage(Message, EditorState),
+    DeleteMessage(Message),
+    Moderate(ModerateState),
+    Bans(BansState),
+    Ignores(IgnoresState),
     Account(AccountUiState),
     Links(LinksState),
-    InspectMessage(Message),
+    Search(SearchState),
+    EmojiPicker(EmojiPickerState),
+    InspectMessage(Message, Vec<(Option<Time>, String)>),
     InspectSession(SessionInfo),
 }
 
 pub struct EuphRoom {
+    keys: &'static Keys,
+    timestamps: &'static Timestamps,
+    notify_command: Option<&'static [String]>,
     server_config: ServerConfig,
     config: config::EuphRoom,
     ui_event_tx: mpsc::UnboundedSender<UiEvent>,
@@ -62,18 +82,30 @@ pub struct EuphRoom {
 
     chat: ChatState<euph::SmallMessage, EuphRoomVault>,
     last_msg_sent: Option<oneshot::Receiver<MessageId>>,
+    /// Last cursor position checked for a truncated message.
+    untruncate_checked: Option<MessageId>,
 
     nick_list: ListState<SessionId>,
+    /// Users banned during this session, for the bans popup.
+    bans: Vec<UserId>,
+
+    completion: Option<Completion>,
 }
 
 impl EuphRoom {
     pub fn new(
+        keys: &'static Keys,
+        timestamps: &'static Timestamps,
+        notify_command: Option<&'static [String]>,
         server_config: ServerConfig,
         config: config::EuphRoom,
         vault: EuphRoomVault,
         ui_event_tx: mpsc::UnboundedSender<UiEvent>,
     ) -> Self {
         Self {
+            keys,
+            timestamps,
+            notify_command,
             server_config,
             config,
             ui_event_tx,
@@ -81,9 +113,12 @@ impl EuphRoom {
             focus: Focus::Chat,
             state: State::Normal,
             popups: VecDeque::new(),
-            chat: ChatState::new(vault),
+            chat: ChatState::new(vault, keys, timestamps),
             last_msg_sent: None,
+            untruncate_checked: None,
             nick_list: ListState::new(),
+            bans: vec![],
+            completion: None,
         }
     }
 
@@ -113,6 +148,8 @@ impl EuphRoom {
             self.room = Some(euph::Room::new(
                 self.vault().clone(),
                 instance_config,
+                self.config.notify,
+                self.notify_command,
                 move |e| {
                     let _ = tx.send(UiEvent::Euph(e));
                 },
@@ -150,22 +187,49 @@ impl EuphRoom {
         logging_unwrap!(self.vault().unseen_msgs_count().await)
     }
 
+    pub async fn has_drafts(&self) -> bool {
+        logging_unwrap!(self.vault().has_drafts().await)
+    }
+
+    /// Save the draft currently being composed, see [`ChatState::save_draft`].
+    pub async fn save_draft(&self, immediately: bool) {
+        logging_unwrap!(self.chat.save_draft(immediately).await);
+    }
+
     async fn stabilize_pseudo_msg(&mut self) {
         if let Some(id_rx) = &mut self.last_msg_sent {
             match id_rx.try_recv() {
                 Ok(id) => {
-                    self.chat.sent(Some(id)).await;
+                    logging_unwrap!(self.chat.sent(Some(id)).await);
                     self.last_msg_sent = None;
                 }
                 Err(TryRecvError::Empty) => {} // Wait a bit longer
                 Err(TryRecvError::Closed) => {
-                    self.chat.sent(None).await;
+                    logging_unwrap!(self.chat.sent(None).await);
                     self.last_msg_sent = None;
                 }
             }
         }
     }
 
+    /// Fetch the full content of the message under the cursor if it is
+    /// truncated.
+    async fn stabilize_untruncate(&mut self) {
+        let cursor = self.chat.cursor().await;
+        if cursor == self.untruncate_checked {
+            return;
+        }
+        let (Some(room), Some(id)) = (&self.room, cursor) else {
+            return;
+        };
+        if let Some(msg) = logging_unwrap!(self.vault().full_msg(id).await) {
+            if msg.truncated {
+                let _ = room.untruncate(id);
+            }
+        }
+        self.untruncate_checked = cursor;
+    }
+
     fn stabilize_focus(&mut self) {
         match self.room_state() {
             Some(euph::State::Connected(_, conn::State::Joined(_))) => {}
@@ -190,6 +254,21 @@ impl EuphRoom {
             (State::Nick(_), Some(euph::State::Connected(_, conn::State::Joined(_)))) => {}
             (State::Nick(_), _) => self.state = State::Normal,
 
+            (
+                State::EditMessage(_, _)
+                | State::DeleteMessage(_)
+                | State::Moderate(_)
+                | State::Bans(_),
+                Some(euph::State::Connected(_, conn::State::Joined(joined))),
+            ) if joined.session.is_manager => {}
+            (
+                State::EditMessage(_, _)
+                | State::DeleteMessage(_)
+                | State::Moderate(_)
+                | State::Bans(_),
+                _,
+            ) => self.state = State::Normal,
+
             (State::Account(account), state) => {
                 if !account.stabilize(state) {
                     self.state = State::Normal
@@ -202,6 +281,7 @@ impl EuphRoom {
 
     async fn stabilize(&mut self) {
         self.stabilize_pseudo_msg().await;
+        self.stabilize_untruncate().await;
         self.stabilize_focus();
         self.stabilize_state();
     }
@@ -223,12 +303,25 @@ impl EuphRoom {
             State::Normal => {}
             State::Auth(editor) => layers.push(auth::widget(editor)),
             State::Nick(editor) => layers.push(nick::widget(editor)),
+            State::EditMessage(msg, editor) => layers.push(edit_msg::widget(msg, editor)),
+            State::DeleteMessage(msg) => layers.push(delete_msg::widget(msg)),
+            State::Moderate(moderate) => layers.push(moderate.widget()),
+            State::Bans(bans) => layers.push(bans.widget(self.keys)),
+            State::Ignores(ignores) => layers.push(ignores.widget(self.keys)),
             State::Account(account) => layers.push(account.widget()),
             State::Links(links) => layers.push(links.widget()),
-            State::InspectMessage(message) => layers.push(inspect::message_widget(message)),
+            State::Search(search) => layers.push(search.widget(self.timestamps)),
+            State::EmojiPicker(picker) => layers.push(picker.widget()),
+            State::InspectMessage(message, history) => {
+                layers.push(inspect::message_widget(message, history, self.timestamps))
+            }
             State::InspectSession(session) => layers.push(inspect::session_widget(session)),
         }
 
+        if let Some(completion) = &self.completion {
+            layers.push(completion.widget());
+        }
+
         for popup in &self.popups {
             layers.push(popup.widget());
         }
@@ -301,6 +394,20 @@ impl EuphRoom {
             }
         };
 
+        if let Some(progress) = self.room.as_ref().and_then(|r| r.sync_progress()) {
+            let action = if progress.verify {
+                "verifying"
+            } else {
+                "downloading"
+            };
+            info = info.then_plain(format!(", {action} history ({} msgs", progress.msgs));
+            if let Some(oldest) = progress.oldest {
+                let date = self.timestamps.time_zone.convert(oldest.0).date();
+                info = info.then_plain(format!(", back to {date}"));
+            }
+            info = info.then_plain(")");
+        }
+
         let unseen = self.unseen_msgs_count().await;
         if unseen > 0 {
             info = info
@@ -344,7 +451,7 @@ impl EuphRoom {
                 if let Some(room) = &self.room {
                     match room.send(parent, content) {
                         Ok(id_rx) => self.last_msg_sent = Some(id_rx),
-                        Err(_) => self.chat.sent(None).await,
+                        Err(_) => logging_unwrap!(self.chat.sent(None).await),
                     }
                     return true;
                 }
@@ -362,6 +469,7 @@ impl EuphRoom {
     }
 
     fn list_room_key_bindings(&self, bindings: &mut KeyBindingsList) {
+        let keys = self.keys;
         match self.room_state() {
             // Authenticating
             Some(euph::State::Connected(
@@ -370,14 +478,27 @@ impl EuphRoom {
                     bounce: Some(_), ..
                 }),
             )) => {
-                bindings.binding("a", "authenticate");
+                bindings.action(&keys.room.authenticate, "authenticate");
             }
 
             // Connected
-            Some(euph::State::Connected(_, conn::State::Joined(_))) => {
-                bindings.binding("n", "change nick");
-                bindings.binding("m", "download more messages");
-                bindings.binding("A", "show account ui");
+            Some(euph::State::Connected(_, conn::State::Joined(joined))) => {
+                bindings.action(&keys.room.nick, "change nick");
+                bindings.action(&keys.room.more_messages, "download more messages");
+                bindings.action(
+                    &keys.room.download_history,
+                    "start/stop downloading full room history",
+                );
+                bindings.action(
+                    &keys.room.verify_history,
+                    "start/stop redownloading known messages",
+                );
+                bindings.action(&keys.room.account, "show account ui");
+                if joined.session.is_manager {
+                    bindings.action(&keys.room.edit_message, "edit message");
+                    bindings.action(&keys.room.delete_message, "delete message");
+                    bindings.action(&keys.room.bans, "show bans");
+                }
             }
 
             // Otherwise
@@ -385,12 +506,15 @@ impl EuphRoom {
         }
 
         // Inspecting messages
-        bindings.binding("i", "inspect message");
-        bindings.binding("I", "show message links");
-        bindings.binding("ctrl+p", "open room's plugh.de/present page");
+        bindings.action(&keys.room.inspect, "inspect message");
+        bindings.action(&keys.room.links, "show message links");
+        bindings.action(&keys.room.search, "search messages");
+        bindings.action(&keys.room.ignores, "show ignored users");
+        bindings.action(&keys.room.present, "open room's plugh.de/present page");
     }
 
     async fn handle_room_input_event(&mut self, event: &InputEvent) -> bool {
+        let keys = self.keys;
         match self.room_state() {
             // Authenticating
             Some(euph::State::Connected(
@@ -398,65 +522,103 @@ impl EuphRoom {
                 conn::State::Joining(Joining {
                     bounce: Some(_), ..
                 }),
-            )) => {
-                if let key!('a') = event {
-                    self.state = State::Auth(auth::new());
-                    return true;
-                }
+            )) if keys.room.authenticate.matches(event) => {
+                self.state = State::Auth(auth::new());
+                return true;
             }
 
             // Joined
-            Some(euph::State::Connected(_, conn::State::Joined(joined))) => match event {
-                key!('n') | key!('N') => {
+            Some(euph::State::Connected(_, conn::State::Joined(joined))) => {
+                if keys.room.nick.matches(event) {
                     self.state = State::Nick(nick::new(joined.clone()));
                     return true;
                 }
-                key!('m') => {
+                if keys.room.more_messages.matches(event) {
                     if let Some(room) = &self.room {
                         let _ = room.log();
                     }
                     return true;
                 }
-                key!('A') => {
+                let download = keys.room.download_history.matches(event);
+                let verify = keys.room.verify_history.matches(event);
+                if download || verify {
+                    if let Some(room) = &mut self.room {
+                        if room.sync_progress().is_some() {
+                            room.stop_sync();
+                        } else if !room.start_sync(verify).await {
+                            self.popups.push_front(RoomPopup::Error {
+                                description: "Failed to download room history.".to_string(),
+                                reason: "room is ephemeral".to_string(),
+                            });
+                        }
+                    }
+                    return true;
+                }
+                if keys.room.account.matches(event) {
                     self.state = State::Account(AccountUiState::new());
                     return true;
                 }
-                _ => {}
-            },
+                let edit = keys.room.edit_message.matches(event);
+                let delete = keys.room.delete_message.matches(event);
+                if joined.session.is_manager && (edit || delete) {
+                    if let Some(id) = self.chat.cursor().await {
+                        if let Some(msg) = logging_unwrap!(self.vault().full_msg(id).await) {
+                            self.state = if edit {
+                                let editor = edit_msg::new(&msg);
+                                State::EditMessage(msg, editor)
+                            } else {
+                                State::DeleteMessage(msg)
+                            };
+                        }
+                    }
+                    return true;
+                }
+                if joined.session.is_manager && keys.room.bans.matches(event) {
+                    self.state = State::Bans(BansState::new(self.bans.clone()));
+                    return true;
+                }
+            }
 
             // Otherwise
             _ => {}
         }
 
         // Always applicable
-        match event {
-            key!('i') => {
-                if let Some(id) = self.chat.cursor().await {
-                    if let Some(msg) = logging_unwrap!(self.vault().full_msg(id).await) {
-                        self.state = State::InspectMessage(msg);
-                    }
+        if keys.room.inspect.matches(event) {
+            if let Some(id) = self.chat.cursor().await {
+                if let Some(msg) = logging_unwrap!(self.vault().full_msg(id).await) {
+                    let history = logging_unwrap!(self.vault().msg_history(id).await);
+                    self.state = State::InspectMessage(msg, history);
                 }
-                return true;
             }
-            key!('I') => {
-                if let Some(id) = self.chat.cursor().await {
-                    if let Some(msg) = logging_unwrap!(self.vault().msg(id).await) {
-                        self.state = State::Links(LinksState::new(&msg.content));
-                    }
+            return true;
+        }
+        if keys.room.links.matches(event) {
+            if let Some(id) = self.chat.cursor().await {
+                if let Some(msg) = logging_unwrap!(self.vault().msg(id).await) {
+                    self.state = State::Links(LinksState::new(&msg.content));
                 }
-                return true;
             }
-            key!(Ctrl + 'p') => {
-                let link = format!("https://plugh.de/present/{}/", self.name());
-                if let Err(error) = open::that(&link) {
-                    self.popups.push_front(RoomPopup::Error {
-                        description: format!("Failed to open link: {link}"),
-                        reason: format!("{error}"),
-                    });
-                }
-                return true;
+            return true;
+        }
+        if keys.room.search.matches(event) {
+            self.state = State::Search(SearchState::new());
+            return true;
+        }
+        if keys.room.ignores.matches(event) {
+            let ignores = logging_unwrap!(self.vault().vault().ignores().await);
+            self.state = State::Ignores(IgnoresState::new(ignores));
+            return true;
+        }
+        if keys.room.present.matches(event) {
+            let link = format!("https://plugh.de/present/{}/", self.name());
+            if let Err(error) = open::that(&link) {
+                self.popups.push_front(RoomPopup::Error {
+                    description: format!("Failed to open link: {link}"),
+                    reason: format!("{error}"),
+                });
             }
-            _ => {}
+            return true;
         }
 
         false
@@ -466,6 +628,80 @@ impl EuphRoom {
         self.list_room_key_bindings(bindings);
         bindings.empty();
         self.list_chat_key_bindings(bindings).await;
+        if self.chat.editor().await.is_some() {
+            bindings.binding("tab, shift+tab", "complete @nick, &room or :emoji:");
+            bindings.action(&self.keys.room.emoji, "pick emoji");
+            if self.completion.is_some() {
+                bindings.binding("esc", "abort completion");
+            }
+        }
+    }
+
+    /// Nicks to suggest when completing mentions, most likely ones first.
+    async fn completion_nicks(&self) -> Vec<String> {
+        let recent = logging_unwrap!(self.vault().recent_nicks(RECENT_NICKS).await);
+
+        let mut nicks = vec![];
+        if let Some(euph::State::Connected(_, conn::State::Joined(joined))) = self.room_state() {
+            nicks = joined
+                .listing
+                .values()
+                .map(|session| match session {
+                    SessionInfo::Full(session) => session.name.clone(),
+                    SessionInfo::Partial(nick) => nick.to.clone(),
+                })
+                .filter(|nick| !nick.is_empty())
+                .collect::<Vec<_>>();
+        }
+
+        // People present in the room who have spoken recently are the most
+        // likely to be mentioned, followed by the other people present.
+        nicks.sort_unstable_by_key(|nick| {
+            let position = recent.iter().position(|r| r == nick);
+            (position.unwrap_or(usize::MAX), nick.clone())
+        });
+        nicks.extend(recent);
+        nicks
+    }
+
+    async fn handle_completion_input_event(
+        &mut self,
+        terminal: &mut Terminal,
+        event: &InputEvent,
+    ) -> bool {
+        let Some(editor) = self.chat.editor().await else {
+            self.completion = None;
+            return false;
+        };
+
+        match event {
+            key!(Tab) => {
+                if self.completion.is_none() {
+                    let nicks = self.completion_nicks().await;
+                    let rooms = logging_unwrap!(self.vault().vault().rooms().await);
+                    self.completion = Completion::new(&editor, &nicks, &rooms);
+                }
+                if let Some(completion) = &mut self.completion {
+                    completion.next(&editor, terminal.widthdb());
+                    return true;
+                }
+            }
+            key!(BackTab) | key!(Shift + BackTab) => {
+                if let Some(completion) = &mut self.completion {
+                    completion.prev(&editor, terminal.widthdb());
+                    return true;
+                }
+            }
+            key!(Esc) => {
+                if let Some(mut completion) = self.completion.take() {
+                    completion.abort(&editor, terminal.widthdb());
+                    return true;
+                }
+            }
+            _ => self.completion = None,
+        }
+
+        false
     }
 
     async fn handle_chat_focus_input_event(
@@ -474,6 +710,16 @@ impl EuphRoom {
         crossterm_lock: &Arc<FairMutex<()>>,
         event: &InputEvent,
     ) -> bool {
+        if self.handle_completion_input_event(terminal, event).await {
+            logging_unwrap!(self.chat.save_draft(false).await);
+            return true;
+        }
+
+        if self.keys.room.emoji.matches(event) && self.chat.editor().await.is_some() {
+            self.state = State::EmojiPicker(EmojiPickerState::new());
+            return true;
+        }
+
         // We need to handle chat input first, otherwise the other
         // key bindings will shadow characters in the editor.
         if self
@@ -491,17 +737,25 @@ impl EuphRoom {
     }
 
     fn list_nick_list_focus_key_bindings(&self, bindings: &mut KeyBindingsList) {
-        util::list_list_key_bindings(bindings);
-
-        bindings.binding("i", "inspect session");
+        util::list_list_key_bindings(bindings, self.keys);
+
+        bindings.action(&self.keys.room.inspect, "inspect session");
+        bindings.action(&self.keys.room.pm, "start private conversation");
+        bindings.action(&self.keys.room.ignore, "toggle ignoring user");
+        bindings.action(&self.keys.room.ignores, "show ignored users");
+        if let Some(euph::State::Connected(_, conn::State::Joined(joined))) = self.room_state() {
+            if joined.session.is_manager {
+                bindings.action(&self.keys.room.moderate, "moderate session");
+            }
+        }
     }
 
-    fn handle_nick_list_focus_input_event(&mut self, event: &InputEvent) -> bool {
-        if util::handle_list_input_event(&mut self.nick_list, event) {
+    async fn handle_nick_list_focus_input_event(&mut self, event: &InputEvent) -> bool {
+        if util::handle_list_input_event(&mut self.nick_list, event, self.keys) {
             return true;
         }
 
-        if let key!('i') = event {
+        if self.keys.room.inspect.matches(event) {
             if let Some(euph::State::Connected(_, conn::State::Joined(joined))) = self.room_state()
             {
                 if let Some(id) = self.nick_list.cursor() {
@@ -516,6 +770,85 @@ impl EuphRoom {
             return true;
         }
 
+        if self.keys.room.pm.matches(event) {
+            if let Some(euph::State::Connected(_, conn::State::Joined(joined))) = self.room_state()
+            {
+                // Our own session is not in the listing, so we never pm ourselves.
+                let user_id = match self
+                    .nick_list
+                    .cursor()
+                    .and_then(|id| joined.listing.get(&id))
+                {
+                    Some(SessionInfo::Full(session)) => Some(session.id.clone()),
+                    Some(SessionInfo::Partial(nick)) => Some(nick.id.clone()),
+                    None => None,
+                };
+                if let (Some(room), Some(user_id)) = (&self.room, user_id) {
+                    let _ = room.pm_initiate(user_id);
+                }
+            }
+            return true;
+        }
+
+        if self.keys.room.ignore.matches(event) {
+            if let Some(euph::State::Connected(_, conn::State::Joined(joined))) = self.room_state()
+            {
+                // Our own session is not in the listing, so we can't ignore
+                // ourselves by accident.
+                let user_id = match self
+                    .nick_list
+                    .cursor()
+                    .and_then(|id| joined.listing.get(&id))
+                {
+                    Some(SessionInfo::Full(session)) => Some(session.id.clone()),
+                    Some(SessionInfo::Partial(nick)) => Some(nick.id.clone()),
+                    None => None,
+                };
+                if let Some(user_id) = user_id {
+                    let vault = self.vault().vault();
+                    let ignore = euph::Ignore::UserId(user_id);
+                    if logging_unwrap!(vault.ignores().await).contains(&ignore) {
+                        logging_unwrap!(vault.remove_ignore(ignore).await);
+                    } else {
+                        logging_unwrap!(vault.add_ignore(ignore).await);
+                    }
+                }
+            }
+            return true;
+        }
+
+        if self.keys.room.ignores.matches(event) {
+            let ignores = logging_unwrap!(self.vault().vault().ignores().await);
+            self.state = State::Ignores(IgnoresState::new(ignores));
+            return true;
+        }
+
+        if self.keys.room.moderate.matches(event) {
+            if let Some(euph::State::Connected(_, conn::State::Joined(joined))) = self.room_state()
+            {
+                if !joined.session.is_manager {
+                    return false;
+                }
+                // Moderating ourselves makes little sense, so our own session
+                // is ignored here as well.
+                let target = match self
+                    .nick_list
+                    .cursor()
+                    .and_then(|id| joined.listing.get(&id))
+                {
+                    Some(SessionInfo::Full(session)) => {
+                        Some((session.id.clone(), session.name.clone()))
+                    }
+                    Some(SessionInfo::Partial(nick)) => Some((nick.id.clone(), nick.to.clone())),
+                    None => None,
+                };
+                if let Some((user_id, nick)) = target {
+                    self.state = State::Moderate(ModerateState::new(user_id, nick));
+                }
+            }
+            return true;
+        }
+
         false
     }
 
@@ -526,13 +859,17 @@ impl EuphRoom {
         match self.focus {
             Focus::Chat => {
                 if let Some(euph::State::Connected(_, conn::State::Joined(_))) = self.room_state() {
-                    bindings.binding("tab", "focus on nick list");
+                    bindings.action(&self.keys.room.focus, "focus on nick list");
                 }
 
                 self.list_chat_focus_key_bindings(bindings).await;
             }
             Focus::NickList => {
-                bindings.binding("tab, esc", "focus on chat");
+                if self.keys.room.focus.is_empty() {
+                    bindings.binding("esc", "focus on chat");
+                } else {
+                    bindings.binding(&format!("{}, esc", self.keys.room.focus), "focus on chat");
+                }
                 bindings.empty();
                 bindings.heading("Nick list");
                 self.list_nick_list_focus_key_bindings(bindings);
@@ -558,19 +895,19 @@ impl EuphRoom {
                 }
 
                 if let Some(euph::State::Connected(_, conn::State::Joined(_))) = self.room_state() {
-                    if let key!(Tab) = event {
+                    if self.keys.room.focus.matches(event) {
                         self.focus = Focus::NickList;
                         return true;
                     }
                 }
             }
             Focus::NickList => {
-                if let key!(Tab) | key!(Esc) = event {
+                if matches!(event, key!(Esc)) || self.keys.room.focus.matches(event) {
                     self.focus = Focus::Chat;
                     return true;
                 }
 
-                if self.handle_nick_list_focus_input_event(event) {
+                if self.handle_nick_list_focus_input_event(event).await {
                     return true;
                 }
             }
@@ -582,7 +919,10 @@ impl EuphRoom {
     pub async fn list_key_bindings(&self, bindings: &mut KeyBindingsList) {
         bindings.heading("Room");
 
-        if !self.popups.is_empty() {
+        if let Some(popup) = self.popups.back() {
+            if let RoomPopup::Pm { .. } = popup {
+                bindings.binding("enter", "join private conversation");
+            }
             bindings.binding("esc", "close popup");
             return;
         }
@@ -591,9 +931,16 @@ impl EuphRoom {
             State::Normal => self.list_normal_key_bindings(bindings).await,
             State::Auth(_) => auth::list_key_bindings(bindings),
             State::Nick(_) => nick::list_key_bindings(bindings),
+            State::EditMessage(_, _) => edit_msg::list_key_bindings(bindings),
+            State::DeleteMessage(_) => delete_msg::list_key_bindings(bindings),
+            State::Moderate(moderate) => moderate.list_key_bindings(bindings, self.keys),
+            State::Bans(bans) => bans.list_key_bindings(bindings, self.keys),
+            State::Ignores(ignores) => ignores.list_key_bindings(bindings, self.keys),
             State::Account(account) => account.list_key_bindings(bindings),
-            State::Links(links) => links.list_key_bindings(bindings),
-            State::InspectMessage(_) | State::InspectSession(_) => {
+            State::Links(links) => links.list_key_bindings(bindings, self.keys),
+            State::Search(search) => search.list_key_bindings(bindings, self.keys),
+            State::EmojiPicker(picker) => picker.list_key_bindings(bindings, self.keys),
+            State::InspectMessage(_, _) | State::InspectSession(_) => {
                 inspect::list_key_bindings(bindings)
             }
         }
@@ -605,10 +952,20 @@ impl EuphRoom {
         crossterm_lock: &Arc<FairMutex<()>>,
         event: &InputEvent,
     ) -> bool {
-        if !self.popups.is_empty() {
-            if matches!(event, key!(Esc)) {
-                self.popups.pop_back();
-                return true;
+        if let Some(popup) = self.popups.back() {
+            match event {
+                key!(Esc) => {
+                    self.popups.pop_back();
+                    return true;
+                }
+                key!(Enter) => {
+                    if let RoomPopup::Pm { room, .. } = popup {
+                        let _ = self.ui_event_tx.send(UiEvent::JoinRoom(room.clone()));
+                        self.popups.pop_back();
+                        return true;
+                    }
+                }
+                _ => {}
             }
             return false;
         }
@@ -640,6 +997,73 @@ impl EuphRoom {
                     }
                 }
             }
+            State::EditMessage(msg, editor) => {
+                match edit_msg::handle_input_event(terminal, event, &self.room, msg, editor) {
+                    edit_msg::EventResult::NotHandled => false,
+                    edit_msg::EventResult::Handled => true,
+                    edit_msg::EventResult::ResetState => {
+                        self.state = State::Normal;
+                        true
+                    }
+                }
+            }
+            State::DeleteMessage(msg) => {
+                match delete_msg::handle_input_event(event, &self.room, msg) {
+                    delete_msg::EventResult::NotHandled => false,
+                    delete_msg::EventResult::ResetState => {
+                        self.state = State::Normal;
+                        true
+                    }
+                }
+            }
+            State::Moderate(moderate) => {
+                match moderate.handle_input_event(terminal, event, &self.room, self.keys) {
+                    moderate::EventResult::NotHandled => false,
+                    moderate::EventResult::Handled => true,
+                    moderate::EventResult::ResetState => {
+                        self.state = State::Normal;
+                        true
+                    }
+                    moderate::EventResult::Banned(id) => {
+                        if !self.bans.contains(&id) {
+                            self.bans.push(id);
+                        }
+                        self.state = State::Normal;
+                        true
+                    }
+                }
+            }
+            State::Bans(bans) => {
+                match bans.handle_input_event(terminal, event, &self.room, self.keys) {
+                    bans::EventResult::NotHandled => false,
+                    bans::EventResult::Handled => true,
+                    bans::EventResult::Close => {
+                        self.state = State::Normal;
+                        true
+                    }
+                    bans::EventResult::Unbanned(id) => {
+                        self.bans.retain(|ban| *ban != id);
+                        true
+                    }
+                }
+            }
+            State::Ignores(ignores) => match ignores.handle_input_event(terminal, event, self.keys)
+            {
+                ignores::EventResult::NotHandled => false,
+                ignores::EventResult::Handled => true,
+                ignores::EventResult::Close => {
+                    self.state = State::Normal;
+                    true
+                }
+                ignores::EventResult::Added(ignore) => {
+                    logging_unwrap!(self.vault().vault().add_ignore(ignore).await);
+                    true
+                }
+                ignores::EventResult::Removed(ignore) => {
+                    logging_unwrap!(self.vault().vault().remove_ignore(ignore).await);
+                    true
+                }
+            },
             State::Account(account) => {
                 match account.handle_input_event(terminal, event, &self.room) {
                     account::EventResult::NotHandled => false,
@@ -650,7 +1074,7 @@ impl EuphRoom {
                     }
                 }
             }
-            State::Links(links) => match links.handle_input_event(event) {
+            State::Links(links) => match links.handle_input_event(event, self.keys) {
                 links::EventResult::NotHandled => false,
                 links::EventResult::Handled => true,
                 links::EventResult::Close => {
@@ -665,7 +1089,44 @@ impl EuphRoom {
                     true
                 }
             },
-            State::InspectMessage(_) | State::InspectSession(_) => {
+            State::Search(search) => {
+                let vault = self.chat.store().clone();
+                match search
+                    .handle_input_event(terminal, event, &vault, self.keys)
+                    .await
+                {
+                    search::EventResult::NotHandled => false,
+                    search::EventResult::Handled => true,
+                    search::EventResult::Close => {
+                        self.state = State::Normal;
+                        true
+                    }
+                    search::EventResult::JumpTo(id) => {
+                        logging_unwrap!(self.chat.jump_to(id).await);
+                        self.state = State::Normal;
+                        true
+                    }
+                }
+            }
+            State::EmojiPicker(picker) => {
+                match picker.handle_input_event(terminal, event, self.keys) {
+                    emoji::EventResult::NotHandled => false,
+                    emoji::EventResult::Handled => true,
+                    emoji::EventResult::Close => {
+                        self.state = State::Normal;
+                        true
+                    }
+                    emoji::EventResult::Insert(code) => {
+                        if let Some(editor) = self.chat.editor().await {
+                            editor.insert_str(terminal.widthdb(), &code);
+                            logging_unwrap!(self.chat.save_draft(false).await);
+                        }
+                        self.state = State::Normal;
+                        true
+                    }
+                }
+            }
+            State::InspectMessage(_, _) | State::InspectSession(_) => {
                 match inspect::handle_input_event(event) {
                     inspect::EventResult::NotHandled => false,
                     inspect::EventResult::Close => {
@@ -746,6 +1207,23 @@ impl EuphRoom {
             });
         }
 
+        match data {
+            Data::PmInitiateEvent(event) => {
+                self.popups.push_front(RoomPopup::Pm {
+                    from_nick: event.from_nick.clone(),
+                    from_room: event.from_room.clone(),
+                    room: format!("pm:{}", event.pm_id.0),
+                });
+            }
+            Data::PmInitiateReply(reply) => {
+                // We only ever initiate pms when the user explicitly asks us
+                // to, so the room can be joined right away.
+                let room = format!("pm:{}", reply.pm_id.0);
+                let _ = self.ui_event_tx.send(UiEvent::JoinRoom(room));
+            }
+            _ => {}
+        }
+
         handled
     }
 
@@ -0,0 +1,199 @@
+use crossterm::style::{ContentStyle, Stylize};
+use euphoxide::api::MessageId;
+use time::format_description::FormatItem;
+use time::macros::format_description;
+use toss::styled::Styled;
+use toss::terminal::Terminal;
+
+use crate::config::{Keys, Timestamps};
+use crate::euph::{self, SmallMessage};
+use crate::macros::logging_unwrap;
+use crate::ui::input::{key, InputEvent, KeyBindingsList};
+use crate::ui::util;
+use crate::ui::widgets::editor::EditorState;
+use crate::ui::widgets::empty::Empty;
+use crate::ui::widgets::join::{HJoin, Segment, VJoin};
+use crate::ui::widgets::list::ListState;
+use crate::ui::widgets::popup::Popup;
+use crate::ui::widgets::resize::Resize;
+use crate::ui::widgets::rules::HRule;
+use crate::ui::widgets::text::Text;
+use crate::ui::widgets::BoxedWidget;
+use crate::vault::EuphRoomVault;
+
+const TIME_FORMAT: &[FormatItem<'_>] = format_description!("[year]-[month]-[day] [hour]:[minute]");
+
+/// How many results to load at once.
+const LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Query,
+    Results,
+}
+
+pub struct SearchState {
+    focus: Focus,
+    query: EditorState,
+    /// The query the current results belong to.
+    searched: String,
+    results: Vec<SmallMessage>,
+    list: ListState<MessageId>,
+}
+
+pub enum EventResult {
+    NotHandled,
+    Handled,
+    Close,
+    JumpTo(MessageId),
+}
+
+fn query_char(c: char) -> bool {
+    c != '\n'
+}
+
+fn result_lines(msg: &SmallMessage, timestamps: &Timestamps) -> (Styled, Styled) {
+    let style_selected = ContentStyle::default().black().on_white();
+
+    let time = timestamps.format(msg.time.0, TIME_FORMAT);
+    // Results are only one line high, so newlines would just be confusing.
+    let content = msg.content.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let normal = Styled::new(&time, ContentStyle::default().grey())
+        .then_plain(" [")
+        .and_then(euph::style_nick(&msg.nick, ContentStyle::default()))
+        .then_plain("] ")
+        .then_plain(&content);
+    let selected = Styled::new(
+        format!("{time} [{}] {content}", euph::EMOJI.replace(&msg.nick)),
+        style_selected,
+    );
+
+    (normal, selected)
+}
+
+impl SearchState {
+    pub fn new() -> Self {
+        Self {
+            focus: Focus::Query,
+            query: EditorState::new(),
+            searched: String::new(),
+            results: vec![],
+            list: ListState::new(),
+        }
+    }
+
+    pub fn widget(&self, timestamps: &Timestamps) -> BoxedWidget {
+        let bold = ContentStyle::default().bold();
+
+        let mut list = self.list.widget().focus(self.focus == Focus::Results);
+        if self.searched.is_empty() {
+            list.add_unsel(Text::new((
+                "Press enter to search",
+                ContentStyle::default().grey().italic(),
+            )));
+        } else if self.results.is_empty() {
+            list.add_unsel(Text::new((
+                "No messages found",
+                ContentStyle::default().grey().italic(),
+            )));
+        }
+        for msg in &self.results {
+            let (normal, selected) = result_lines(msg, timestamps);
+            list.add_sel(msg.id, Text::new(normal), Text::new(selected));
+        }
+
+        let inner = VJoin::new(vec![
+            Segment::new(HJoin::new(vec![
+                Segment::new(Text::new(("Search:", bold))),
+                Segment::new(Empty::new().width(1)),
+                Segment::new(self.query.widget().focus(self.focus == Focus::Query)).expanding(true),
+            ])),
+            Segment::new(HRule),
+            Segment::new(list).expanding(true),
+        ]);
+
+        Popup::new(Resize::new(inner).min_width(60))
+            .title("Search messages")
+            .build()
+    }
+
+    async fn search(&mut self, vault: &EuphRoomVault) {
+        let query = self.query.text();
+        self.results = logging_unwrap!(vault.search(query.clone(), LIMIT, None).await);
+        self.searched = query;
+        self.list.move_cursor_to_top();
+        self.focus = Focus::Results;
+    }
+
+    async fn load_more(&mut self, vault: &EuphRoomVault) {
+        if let Some(last) = self.results.last() {
+            let before = Some(last.id);
+            let more = logging_unwrap!(vault.search(self.searched.clone(), LIMIT, before).await);
+            self.results.extend(more);
+        }
+    }
+
+    pub fn list_key_bindings(&self, bindings: &mut KeyBindingsList, keys: &Keys) {
+        match self.focus {
+            Focus::Query => {
+                bindings.binding("esc", "close search popup");
+                bindings.binding("enter", "search");
+                if !self.searched.is_empty() {
+                    bindings.binding("tab", "focus on results");
+                }
+                util::list_editor_key_bindings(bindings, query_char);
+            }
+            Focus::Results => {
+                bindings.binding("esc", "close search popup");
+                bindings.binding("tab", "edit search query");
+                bindings.action(&keys.room.search_query, "edit search query");
+                util::list_list_key_bindings(bindings, keys);
+                bindings.action(&keys.room.search_more, "load more results");
+                bindings.empty();
+                bindings.binding("enter", "jump to message");
+            }
+        }
+    }
+
+    pub async fn handle_input_event(
+        &mut self,
+        terminal: &mut Terminal,
+        event: &InputEvent,
+        vault: &EuphRoomVault,
+        keys: &Keys,
+    ) -> EventResult {
+        if let key!(Esc) = event {
+            return EventResult::Close;
+        }
+
+        match self.focus {
+            Focus::Query => match event {
+                key!(Enter) => self.search(vault).await,
+                key!(Tab) if !self.searched.is_empty() => self.focus = Focus::Results,
+                _ => {
+                    if !util::handle_editor_input_event(&self.query, terminal, event, query_char) {
+                        return EventResult::NotHandled;
+                    }
+                }
+            },
+            Focus::Results if keys.room.search_query.matches(event) => self.focus = Focus::Query,
+            Focus::Results if keys.room.search_more.matches(event) => self.load_more(vault).await,
+            Focus::Results => match event {
+                key!(Tab) => self.focus = Focus::Query,
+                key!(Enter) => {
+                    if let Some(id) = self.list.cursor() {
+                        return EventResult::JumpTo(id);
+                    }
+                }
+                _ => {
+                    if !util::handle_list_input_event(&mut self.list, event, keys) {
+                        return EventResult::NotHandled;
+                    }
+                }
+            },
+        }
+
+        EventResult::Handled
+    }
+}
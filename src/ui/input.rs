@@ -4,6 +4,8 @@ use crossterm::event::{Event, KeyCode, KeyModifiers};
 use crossterm::style::{ContentStyle, Stylize};
 use toss::styled::Styled;
 
+use crate::config::{KeyBinding, Keys};
+
 use super::widgets::background::Background;
 use super::widgets::border::Border;
 use super::widgets::empty::Empty;
@@ -30,6 +32,19 @@ impl InputEvent {
             _ => None,
         }
     }
+
+    /// Whether the event would insert text if it was sent to an editor.
+    pub fn is_text(&self) -> bool {
+        matches!(
+            self,
+            Self::Key(KeyEvent {
+                code: KeyCode::Char(_),
+                ctrl: false,
+                alt: false,
+                ..
+            }) | Self::Paste(_)
+        )
+    }
 }
 
 /// A key event data type that is a bit easier to pattern match on than
@@ -98,13 +113,13 @@ impl KeyBindingsList {
         ContentStyle::default().cyan()
     }
 
-    pub fn widget(self) -> BoxedWidget {
+    pub fn widget(self, keys: &Keys) -> BoxedWidget {
         let binding_style = Self::binding_style();
         Float::new(Layer::new(vec![
             Border::new(Background::new(Padding::new(self.0).horizontal(1))).into(),
             Float::new(
                 Padding::new(Text::new(
-                    Styled::new("jk/↓↑", binding_style)
+                    Styled::new(keys.cursor.down.pair(&keys.cursor.up), binding_style)
                         .then_plain(" to scroll, ")
                         .then("esc", binding_style)
                         .then_plain(" to close"),
@@ -139,6 +154,22 @@ impl KeyBindingsList {
         self.0.add_unsel(widget);
     }
 
+    /// Like [`Self::binding`], but for a configurable key binding. Actions
+    /// without any keys bound to them are not listed.
+    pub fn action(&mut self, binding: &KeyBinding, description: &str) {
+        if !binding.is_empty() {
+            self.binding(&binding.to_string(), description);
+        }
+    }
+
+    /// Like [`Self::action`], but for two related actions, e. g. moving up and
+    /// moving down.
+    pub fn action_pair(&mut self, first: &KeyBinding, second: &KeyBinding, description: &str) {
+        if !first.is_empty() || !second.is_empty() {
+            self.binding(&first.pair(second), description);
+        }
+    }
+
     pub fn binding_ctd(&mut self, description: &str) {
         let widget = HJoin::new(vec![
             Segment::new(Resize::new(Empty::new()).min_width(Self::BINDING_WIDTH)),
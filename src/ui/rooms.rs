@@ -100,6 +100,9 @@ impl Rooms {
     fn get_or_insert_room(&mut self, name: String) -> &mut EuphRoom {
         self.euph_rooms.entry(name.clone()).or_insert_with(|| {
             EuphRoom::new(
+                &self.config.keys,
+                &self.config.timestamps,
+                self.config.notify_command.as_deref(),
                 self.euph_server_config.clone(),
                 self.config.euph_room(&name),
                 self.vault.euph().room(name),
@@ -111,6 +114,9 @@ impl Rooms {
     fn connect_to_room(&mut self, name: String) {
         let room = self.euph_rooms.entry(name.clone()).or_insert_with(|| {
             EuphRoom::new(
+                &self.config.keys,
+                &self.config.timestamps,
+                self.config.notify_command.as_deref(),
                 self.euph_server_config.clone(),
                 self.config.euph_room(&name),
                 self.vault.euph().room(name),
@@ -120,6 +126,12 @@ impl Rooms {
         room.connect(&mut self.euph_next_instance_id);
     }
 
+    /// Connect to a room and show it.
+    pub fn join_room(&mut self, name: String) {
+        self.connect_to_room(name.clone());
+        self.state = State::ShowRoom(name);
+    }
+
     fn connect_to_all_rooms(&mut self) {
         for room in self.euph_rooms.values_mut() {
             room.connect(&mut self.euph_next_instance_id);
@@ -295,30 +307,38 @@ impl Rooms {
         }
     }
 
-    fn format_room_info(state: Option<&euph::State>, unseen: usize) -> Styled {
+    fn format_room_info(state: Option<&euph::State>, unseen: usize, draft: bool) -> Styled {
         let unseen_style = ContentStyle::default().bold().green();
+        let draft_style = ContentStyle::default().italic().yellow();
 
-        let state = Self::format_room_state(state);
-        let unseen = Self::format_unseen_msgs(unseen);
-
-        match (state, unseen) {
-            (None, None) => Styled::default(),
-            (None, Some(u)) => Styled::new_plain(" (")
-                .then(u, unseen_style)
-                .then_plain(")"),
-            (Some(s), None) => Styled::new_plain(" (").then_plain(s).then_plain(")"),
-            (Some(s), Some(u)) => Styled::new_plain(" (")
-                .then_plain(s)
-                .then_plain(", ")
-                .then(u, unseen_style)
-                .then_plain(")"),
+        let mut parts = vec![];
+        if let Some(s) = Self::format_room_state(state) {
+            parts.push(Styled::new_plain(s));
+        }
+        if let Some(u) = Self::format_unseen_msgs(unseen) {
+            parts.push(Styled::new(u, unseen_style));
+        }
+        if draft {
+            parts.push(Styled::new("draft", draft_style));
         }
+
+        if parts.is_empty() {
+            return Styled::default();
+        }
+        let mut result = Styled::new_plain(" (");
+        for (i, part) in parts.into_iter().enumerate() {
+            if i > 0 {
+                result = result.then_plain(", ");
+            }
+            result = result.and_then(part);
+        }
+        result.then_plain(")")
     }
 
-    fn sort_rooms(&self, rooms: &mut [(&String, Option<&euph::State>, usize)]) {
+    fn sort_rooms(&self, rooms: &mut [(&String, Option<&euph::State>, usize, bool)]) {
         match self.order {
-            Order::Alphabet => rooms.sort_unstable_by_key(|(n, _, _)| *n),
-            Order::Importance => rooms.sort_unstable_by_key(|(n, s, u)| {
+            Order::Alphabet => rooms.sort_unstable_by_key(|(n, _, _, _)| *n),
+            Order::Importance => rooms.sort_unstable_by_key(|(n, s, u, _)| {
                 let no_instance = matches!(s, None | Some(euph::State::Disconnected));
                 (no_instance, *u == 0, *n)
             }),
@@ -337,17 +357,18 @@ impl Rooms {
         for (name, room) in &self.euph_rooms {
             let state = room.room_state();
             let unseen = room.unseen_msgs_count().await;
-            rooms.push((name, state, unseen));
+            let draft = room.has_drafts().await;
+            rooms.push((name, state, unseen, draft));
         }
         self.sort_rooms(&mut rooms);
-        for (name, state, unseen) in rooms {
+        for (name, state, unseen, draft) in rooms {
             let room_style = ContentStyle::default().bold().blue();
             let room_sel_style = ContentStyle::default().bold().black().on_white();
 
             let mut normal = Styled::new(format!("&{name}"), room_style);
             let mut selected = Styled::new(format!("&{name}"), room_sel_style);
 
-            let info = Self::format_room_info(state, unseen);
+            let info = Self::format_room_info(state, unseen, draft);
             normal = normal.and_then(info.clone());
             selected = selected.and_then(info);
 
@@ -371,104 +392,90 @@ impl Rooms {
         c.is_ascii_alphanumeric() || c == '_'
     }
 
-    fn list_showlist_key_bindings(bindings: &mut KeyBindingsList) {
+    fn list_showlist_key_bindings(&self, bindings: &mut KeyBindingsList) {
+        let keys = &self.config.keys;
         bindings.heading("Rooms");
-        util::list_list_key_bindings(bindings);
+        util::list_list_key_bindings(bindings, keys);
         bindings.empty();
-        bindings.binding("enter", "enter selected room");
-        bindings.binding("c", "connect to selected room");
-        bindings.binding("C", "connect to all rooms");
-        bindings.binding("d", "disconnect from selected room");
-        bindings.binding("D", "disconnect from all rooms");
-        bindings.binding("a", "connect to all autojoin room");
-        bindings.binding("A", "disconnect from all non-autojoin rooms");
-        bindings.binding("n", "connect to new room");
-        bindings.binding("X", "delete room");
+        bindings.action(&keys.rooms.enter, "enter selected room");
+        bindings.action(&keys.rooms.connect, "connect to selected room");
+        bindings.action(&keys.rooms.connect_all, "connect to all rooms");
+        bindings.action(&keys.rooms.disconnect, "disconnect from selected room");
+        bindings.action(&keys.rooms.disconnect_all, "disconnect from all rooms");
+        bindings.action(&keys.rooms.connect_autojoin, "connect to all autojoin room");
+        bindings.action(
+            &keys.rooms.disconnect_non_autojoin,
+            "disconnect from all non-autojoin rooms",
+        );
+        bindings.action(&keys.rooms.new, "connect to new room");
+        bindings.action(&keys.rooms.delete, "delete room");
         bindings.empty();
-        bindings.binding("s", "change sort order");
+        bindings.action(&keys.rooms.change_sort_order, "change sort order");
     }
 
     fn handle_showlist_input_event(&mut self, event: &InputEvent) -> bool {
-        if util::handle_list_input_event(&mut self.list, event) {
+        let keys = &self.config.keys;
+
+        if util::handle_list_input_event(&mut self.list, event, keys) {
             return true;
         }
 
-        match event {
-            key!(Enter) => {
-                if let Some(name) = self.list.cursor() {
-                    self.state = State::ShowRoom(name);
-                }
-                return true;
-            }
-            key!('c') => {
-                if let Some(name) = self.list.cursor() {
-                    self.connect_to_room(name);
-                }
-                return true;
-            }
-            key!('C') => {
-                self.connect_to_all_rooms();
-                return true;
+        if keys.rooms.enter.matches(event) {
+            if let Some(name) = self.list.cursor() {
+                self.state = State::ShowRoom(name);
             }
-            key!('d') => {
-                if let Some(name) = self.list.cursor() {
-                    self.disconnect_from_room(&name);
-                }
-                return true;
+        } else if keys.rooms.connect.matches(event) {
+            if let Some(name) = self.list.cursor() {
+                self.connect_to_room(name);
             }
-            key!('D') => {
-                self.disconnect_from_all_rooms();
-                return true;
+        } else if keys.rooms.connect_all.matches(event) {
+            self.connect_to_all_rooms();
+        } else if keys.rooms.disconnect.matches(event) {
+            if let Some(name) = self.list.cursor() {
+                self.disconnect_from_room(&name);
             }
-            key!('a') => {
-                for (name, options) in &self.config.euph.rooms {
-                    if options.autojoin {
-                        self.connect_to_room(name.clone());
-                    }
+        } else if keys.rooms.disconnect_all.matches(event) {
+            self.disconnect_from_all_rooms();
+        } else if keys.rooms.connect_autojoin.matches(event) {
+            for (name, options) in &self.config.euph.rooms {
+                if options.autojoin {
+                    self.connect_to_room(name.clone());
                 }
-                return true;
-            }
-            key!('A') => {
-                for (name, room) in &mut self.euph_rooms {
-                    let autojoin = self
-                        .config
-                        .euph
-                        .rooms
-                        .get(name)
-                        .map(|r| r.autojoin)
-                        .unwrap_or(false);
-                    if !autojoin {
-                        room.disconnect();
-                    }
-                }
-                return true;
-            }
-            key!('n') => {
-                self.state = State::Connect(EditorState::new());
-                return true;
             }
-            key!('X') => {
-                if let Some(name) = self.list.cursor() {
-                    self.state = State::Delete(name, EditorState::new());
+        } else if keys.rooms.disconnect_non_autojoin.matches(event) {
+            for (name, room) in &mut self.euph_rooms {
+                let autojoin = self
+                    .config
+                    .euph
+                    .rooms
+                    .get(name)
+                    .map(|r| r.autojoin)
+                    .unwrap_or(false);
+                if !autojoin {
+                    room.disconnect();
                 }
-                return true;
             }
-            key!('s') => {
-                self.order = match self.order {
-                    Order::Alphabet => Order::Importance,
-                    Order::Importance => Order::Alphabet,
-                };
-                return true;
+        } else if keys.rooms.new.matches(event) {
+            self.state = State::Connect(EditorState::new());
+        } else if keys.rooms.delete.matches(event) {
+            if let Some(name) = self.list.cursor() {
+                self.state = State::Delete(name, EditorState::new());
             }
-            _ => {}
+        } else if keys.rooms.change_sort_order.matches(event) {
+            self.order = match self.order {
+                Order::Alphabet => Order::Importance,
+                Order::Importance => Order::Alphabet,
+            };
+        } else {
+            return false;
         }
 
-        false
+        true
     }
 
     pub async fn list_key_bindings(&self, bindings: &mut KeyBindingsList) {
         match &self.state {
-            State::ShowList => Self::list_showlist_key_bindings(bindings),
+            State::ShowList => self.list_showlist_key_bindings(bindings),
             State::ShowRoom(name) => {
                 // Key bindings for leaving the room are a part of the room's
                 // list_key_bindings function since they may be shadowed by the
@@ -580,4 +587,14 @@ impl Rooms {
         };
         handled && room_visible
     }
+
+    /// Save the drafts currently being composed in all rooms.
+    ///
+    /// Unless `immediately` is set, only drafts whose saving was delayed for
+    /// long enough are saved.
+    pub async fn save_drafts(&self, immediately: bool) {
+        for room in self.euph_rooms.values() {
+            room.save_draft(immediately).await;
+        }
+    }
 }
@@ -4,6 +4,8 @@ use std::sync::Arc;
 use parking_lot::FairMutex;
 use toss::terminal::Terminal;
 
+use crate::config::Keys;
+
 use super::input::{key, InputEvent, KeyBindingsList};
 use super::widgets::editor::EditorState;
 use super::widgets::list::ListState;
@@ -28,22 +30,36 @@ pub fn prompt(
 // List //
 //////////
 
-pub fn list_list_key_bindings(bindings: &mut KeyBindingsList) {
-    bindings.binding("j/k, ↓/↑", "move cursor up/down");
-    bindings.binding("g, home", "move cursor to top");
-    bindings.binding("G, end", "move cursor to bottom");
-    bindings.binding("ctrl+y/e", "scroll up/down");
+pub fn list_list_key_bindings(bindings: &mut KeyBindingsList, keys: &Keys) {
+    bindings.action_pair(&keys.cursor.down, &keys.cursor.up, "move cursor up/down");
+    bindings.action(&keys.cursor.to_top, "move cursor to top");
+    bindings.action(&keys.cursor.to_bottom, "move cursor to bottom");
+    bindings.action_pair(
+        &keys.scroll.up_line,
+        &keys.scroll.down_line,
+        "scroll up/down",
+    );
 }
 
-pub fn handle_list_input_event<Id: Clone>(list: &mut ListState<Id>, event: &InputEvent) -> bool {
-    match event {
-        key!('k') | key!(Up) => list.move_cursor_up(),
-        key!('j') | key!(Down) => list.move_cursor_down(),
-        key!('g') | key!(Home) => list.move_cursor_to_top(),
-        key!('G') | key!(End) => list.move_cursor_to_bottom(),
-        key!(Ctrl + 'y') => list.scroll_up(1),
-        key!(Ctrl + 'e') => list.scroll_down(1),
-        _ => return false,
+pub fn handle_list_input_event<Id: Clone>(
+    list: &mut ListState<Id>,
+    event: &InputEvent,
+    keys: &Keys,
+) -> bool {
+    if keys.cursor.up.matches(event) {
+        list.move_cursor_up();
+    } else if keys.cursor.down.matches(event) {
+        list.move_cursor_down();
+    } else if keys.cursor.to_top.matches(event) {
+        list.move_cursor_to_top();
+    } else if keys.cursor.to_bottom.matches(event) {
+        list.move_cursor_to_bottom();
+    } else if keys.scroll.up_line.matches(event) {
+        list.scroll_up(1);
+    } else if keys.scroll.down_line.matches(event) {
+        list.scroll_down(1);
+    } else {
+        return false;
     }
 
     true
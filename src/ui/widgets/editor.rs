@@ -1,3 +1,4 @@
+use std::ops::Range;
 use std::sync::Arc;
 use std::{io, iter};
 
@@ -181,6 +182,15 @@ impl InnerEditorState {
         self.record_cursor_col(widthdb);
     }
 
+    /// Replace a range of the text and move the cursor to the end of the
+    /// replacement.
+    fn replace_range(&mut self, widthdb: &mut WidthDb, range: Range<usize>, with: &str) {
+        self.idx = range.start + with.len();
+        self.text.replace_range(range, with);
+        self.move_cursor_to_grapheme_boundary();
+        self.record_cursor_col(widthdb);
+    }
+
     /// Insert a character at the current cursor position and move the cursor
     /// accordingly.
     fn insert_char(&mut self, widthdb: &mut WidthDb, ch: char) {
@@ -319,6 +329,8 @@ impl InnerEditorState {
     }
 }
 
+/// Cloning an editor state results in a handle to the same editor.
+#[derive(Clone)]
 pub struct EditorState(Arc<Mutex<InnerEditorState>>);
 
 impl EditorState {
@@ -347,6 +359,11 @@ impl EditorState {
         self.0.lock().text.clone()
     }
 
+    /// Index of the cursor in the text.
+    pub fn cursor(&self) -> usize {
+        self.0.lock().idx
+    }
+
     pub fn clear(&self) {
         self.0.lock().clear();
     }
@@ -355,6 +372,10 @@ impl EditorState {
         self.0.lock().set_text(widthdb, text);
     }
 
+    pub fn replace_range(&self, widthdb: &mut WidthDb, range: Range<usize>, with: &str) {
+        self.0.lock().replace_range(widthdb, range, with);
+    }
+
     pub fn insert_char(&self, widthdb: &mut WidthDb, ch: char) {
         self.0.lock().insert_char(widthdb, ch);
     }
@@ -4,17 +4,21 @@ mod prepare;
 
 use std::fs;
 use std::path::Path;
+use std::sync::Arc;
 
+use parking_lot::Mutex;
 use rusqlite::Connection;
 use vault::tokio::TokioVault;
 use vault::Action;
 
-pub use self::euph::{EuphRoomVault, EuphVault};
+use self::euph::IgnoreCache;
+pub use self::euph::{EuphRoomVault, EuphVault, ExportFilter};
 
 #[derive(Debug, Clone)]
 pub struct Vault {
     tokio_vault: TokioVault,
     ephemeral: bool,
+    euph_ignores: Arc<Mutex<IgnoreCache>>,
 }
 
 struct GcAction;
@@ -23,7 +27,15 @@ impl Action for GcAction {
     type Result = ();
 
     fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
-        conn.execute_batch("ANALYZE; VACUUM;")
+        // Vacuuming may change the rowids of messages, which the full text
+        // search index refers to.
+        conn.execute_batch(
+            "
+            ANALYZE;
+            VACUUM;
+            INSERT INTO euph_msgs_fts (euph_msgs_fts) VALUES ('rebuild');
+            ",
+        )
     }
 }
 
@@ -41,7 +53,7 @@ impl Vault {
     }
 
     pub fn euph(&self) -> EuphVault {
-        EuphVault::new(self.clone())
+        EuphVault::new(self.clone(), self.euph_ignores.clone())
     }
 }
 
@@ -55,6 +67,7 @@ fn launch_from_connection(conn: Connection, ephemeral: bool) -> rusqlite::Result
     Ok(Vault {
         tokio_vault,
         ephemeral,
+        euph_ignores: Arc::new(Mutex::new(IgnoreCache::default())),
     })
 }
 
@@ -1,15 +1,18 @@
+use std::collections::HashSet;
 use std::mem;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use cookie::{Cookie, CookieJar};
 use euphoxide::api::{Message, MessageId, SessionId, SessionView, Snowflake, Time, UserId};
+use parking_lot::Mutex;
 use rusqlite::types::{FromSql, FromSqlError, ToSqlOutput, Value, ValueRef};
 use rusqlite::{named_params, params, Connection, OptionalExtension, ToSql, Transaction};
 use time::OffsetDateTime;
 use vault::Action;
 
-use crate::euph::SmallMessage;
+use crate::euph::{self, Ignore, IgnoreList, SmallMessage};
 use crate::store::{MsgStore, Path, Tree};
 
 ///////////////////
@@ -54,14 +57,29 @@ impl FromSql for WTime {
 // EuphVault //
 ///////////////
 
+/// The ignore list, prepared for matching messages against it.
+///
+/// Since messages are checked against it whenever they are displayed, it is
+/// only read from the db again after it was changed.
+#[derive(Debug, Default)]
+pub(super) struct IgnoreCache {
+    /// Incremented whenever the ignore list is changed.
+    generation: u64,
+    list: Option<Arc<IgnoreList>>,
+}
+
 #[derive(Debug, Clone)]
 pub struct EuphVault {
     vault: super::Vault,
+    ignore_cache: Arc<Mutex<IgnoreCache>>,
 }
 
 impl EuphVault {
-    pub(crate) fn new(vault: super::Vault) -> Self {
-        Self { vault }
+    pub(super) fn new(vault: super::Vault, ignore_cache: Arc<Mutex<IgnoreCache>>) -> Self {
+        Self {
+            vault,
+            ignore_cache,
+        }
     }
 
     pub fn vault(&self) -> &super::Vault {
@@ -100,6 +118,64 @@ euph_vault_actions! {
     GetCookies : cookies() -> CookieJar;
     SetCookies : set_cookies(cookies: CookieJar) -> ();
     GetRooms : rooms() -> Vec<String>;
+
+    // Ignore
+    GetIgnores : ignores() -> Vec<Ignore>;
+}
+
+// Changing the ignore list must invalidate the cached list, so these actions
+// are not declared via `euph_vault_actions!`.
+
+struct AddIgnore {
+    ignore: Ignore,
+}
+
+struct RemoveIgnore {
+    ignore: Ignore,
+}
+
+impl EuphVault {
+    pub async fn add_ignore(&self, ignore: Ignore) -> vault::tokio::Result<()> {
+        let result = self.vault.tokio_vault.execute(AddIgnore { ignore }).await;
+        self.invalidate_ignore_list();
+        result
+    }
+
+    pub async fn remove_ignore(&self, ignore: Ignore) -> vault::tokio::Result<()> {
+        let result = self
+            .vault
+            .tokio_vault
+            .execute(RemoveIgnore { ignore })
+            .await;
+        self.invalidate_ignore_list();
+        result
+    }
+
+    fn invalidate_ignore_list(&self) {
+        let mut cache = self.ignore_cache.lock();
+        cache.generation += 1;
+        cache.list = None;
+    }
+
+    /// The ignore list, prepared for matching messages against it.
+    pub async fn ignore_list(&self) -> vault::tokio::Result<Arc<IgnoreList>> {
+        let generation = {
+            let cache = self.ignore_cache.lock();
+            if let Some(list) = &cache.list {
+                return Ok(list.clone());
+            }
+            cache.generation
+        };
+
+        let list = Arc::new(IgnoreList::new(&self.ignores().await?));
+
+        // The list may have been changed while it was being read.
+        let mut cache = self.ignore_cache.lock();
+        if cache.generation == generation {
+            cache.list = Some(list.clone());
+        }
+        Ok(list)
+    }
 }
 
 impl Action for GetCookies {
@@ -168,10 +244,187 @@ impl Action for GetRooms {
     }
 }
 
+/// A `LIKE` pattern with `\` as escape char that matches at least all nicks
+/// matching a nick pattern.
+///
+/// Nicks are normalized before matching, which removes and lowercases chars.
+/// Any amount of chars may thus appear between the pattern's chars, and only
+/// ASCII chars can be kept since `LIKE` only ignores their case. The only
+/// non-ASCII chars lowercasing to ASCII chars are `İ` and `K` (Kelvin sign), so
+/// `i` and `k` are dropped as well.
+fn nick_pattern_to_like(pattern: &str) -> String {
+    let mut result = String::from("%");
+    for c in euph::normalize_nick(pattern).chars() {
+        match c {
+            '*' | 'i' | 'k' => {}
+            '%' | '_' | '\\' => {
+                result.push('\\');
+                result.push(c);
+                result.push('%');
+            }
+            c if c.is_ascii() => {
+                result.push(c);
+                result.push('%');
+            }
+            _ => {}
+        }
+    }
+    result
+}
+
+fn ignore_to_row(ignore: &Ignore) -> (&'static str, &str) {
+    match ignore {
+        Ignore::UserId(id) => ("user_id", &id.0),
+        Ignore::Nick(pattern) => ("nick", pattern),
+    }
+}
+
+fn get_ignores(conn: &Connection) -> rusqlite::Result<Vec<Ignore>> {
+    conn.prepare(
+        "
+        SELECT kind, value
+        FROM euph_ignores
+        ORDER BY kind ASC, value ASC
+        ",
+    )?
+    .query_map([], |row| {
+        let kind: String = row.get(0)?;
+        let value: String = row.get(1)?;
+        Ok(match kind.as_str() {
+            "user_id" => Ignore::UserId(UserId(value)),
+            _ => Ignore::Nick(value),
+        })
+    })?
+    .collect::<rusqlite::Result<_>>()
+}
+
+impl Action for GetIgnores {
+    type Result = Vec<Ignore>;
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        get_ignores(conn)
+    }
+}
+
+impl Action for AddIgnore {
+    type Result = ();
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        let tx = conn.transaction()?;
+
+        let (kind, value) = ignore_to_row(&self.ignore);
+        tx.execute(
+            "
+            INSERT OR IGNORE INTO euph_ignores (kind, value)
+            VALUES (?, ?)
+            ",
+            [kind, value],
+        )?;
+
+        // Messages of ignored users don't count as unseen, so the ones that
+        // already arrived are marked as seen. Nick patterns are matched in
+        // Rust, but most messages can already be ruled out by the db.
+        let (user_id, name) = match &self.ignore {
+            Ignore::UserId(id) => (Some(&id.0), None),
+            Ignore::Nick(pattern) => (None, Some(nick_pattern_to_like(pattern))),
+        };
+        let unseen = tx
+            .prepare(
+                r"
+                SELECT room, id, user_id, name
+                FROM euph_msgs
+                WHERE NOT seen
+                AND (user_id = :user_id OR name LIKE :name ESCAPE '\')
+                ",
+            )?
+            .query_map(
+                named_params! { ":user_id": user_id, ":name": name },
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, WSnowflake>(1)?,
+                        UserId(row.get(2)?),
+                        row.get::<_, String>(3)?,
+                    ))
+                },
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut set_seen = tx.prepare(
+            "
+            UPDATE euph_msgs
+            SET seen = TRUE
+            WHERE room = ?
+            AND id = ?
+            ",
+        )?;
+        let ignores = IgnoreList::new(&[self.ignore]);
+        for (room, id, user_id, nick) in unseen {
+            if ignores.matches(&user_id, &nick) {
+                set_seen.execute(params![room, id])?;
+            }
+        }
+        drop(set_seen);
+
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+impl Action for RemoveIgnore {
+    type Result = ();
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        let (kind, value) = ignore_to_row(&self.ignore);
+        conn.execute(
+            "
+            DELETE FROM euph_ignores
+            WHERE kind = ?
+            AND value = ?
+            ",
+            [kind, value],
+        )?;
+        Ok(())
+    }
+}
+
 ///////////////////
 // EuphRoomVault //
 ///////////////////
 
+/// Which messages of a room to export.
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    /// Only messages sent at or after this time.
+    pub since: Option<Time>,
+    /// Only messages sent at or before this time.
+    pub until: Option<Time>,
+    /// Only messages sent by this nick (case insensitive).
+    pub nick: Option<String>,
+    /// Only this message and its replies.
+    pub thread: Option<MessageId>,
+    /// Leave out messages that were deleted or are missing on the server.
+    pub exclude_deleted: bool,
+    /// Only messages newer than this one.
+    pub after: Option<MessageId>,
+    /// Only messages up to and including this one.
+    pub up_to: Option<MessageId>,
+}
+
+impl ExportFilter {
+    /// Whether any message may be excluded by the filter, ignoring the thread.
+    ///
+    /// Since messages are rarely newer than [`Self::up_to`], it is ignored as
+    /// well.
+    fn filters_msgs(&self) -> bool {
+        self.since.is_some()
+            || self.until.is_some()
+            || self.nick.is_some()
+            || self.exclude_deleted
+            || self.after.is_some()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct EuphRoomVault {
     vault: EuphVault,
@@ -212,6 +465,35 @@ macro_rules! euph_room_vault_actions {
     };
 }
 
+/// Like [`euph_room_vault_actions`], but the actions also receive the ignore
+/// list as `ignores`.
+macro_rules! euph_room_vault_ignore_actions {
+    ( $(
+        $struct:ident : $fn:ident ( $( $arg:ident : $arg_ty:ty ),* ) -> $res:ty ;
+    )* ) => {
+        $(
+            struct $struct {
+                room: String,
+                ignores: Arc<IgnoreList>,
+                $( $arg: $arg_ty, )*
+            }
+        )*
+
+        impl EuphRoomVault {
+            $(
+                pub async fn $fn(&self, $( $arg: $arg_ty, )* ) -> vault::tokio::Result<$res> {
+                    let ignores = self.vault.ignore_list().await?;
+                    self.vault.vault.tokio_vault.execute($struct {
+                        room: self.room.clone(),
+                        ignores,
+                        $( $arg, )*
+                    }).await
+                }
+            )*
+        }
+    };
+}
+
 euph_room_vault_actions! {
     // Room
     Join : join(time: Time) -> ();
@@ -220,11 +502,16 @@ euph_room_vault_actions! {
     // Message
     AddMsg : add_msg(msg: Box<Message>, prev_msg_id: Option<MessageId>, own_user_id: Option<UserId>) -> ();
     AddMsgs : add_msgs(msgs: Vec<Message>, next_msg_id: Option<MessageId>, own_user_id: Option<UserId>) -> ();
+    UpdateMsg : update_msg(msg: Box<Message>) -> ();
+    ImportMsgs : import_msgs(msgs: Vec<Message>) -> ();
+    AddSpan : add_span(start: MessageId, end: MessageId) -> ();
+    GetTruncatedMsgIds : truncated_msg_ids() -> Vec<MessageId>;
+    MarkMissing : mark_missing(start: MessageId, end: MessageId, present: Vec<MessageId>) -> ();
     GetLastSpan : last_span() -> Option<(Option<MessageId>, Option<MessageId>)>;
+    GetGaps : gaps() -> Vec<(MessageId, MessageId)>;
     GetPath : path(id: MessageId) -> Path<MessageId>;
-    GetMsg : msg(id: MessageId) -> Option<SmallMessage>;
     GetFullMsg : full_msg(id: MessageId) -> Option<Message>;
-    GetTree : tree(root_id: MessageId) -> Tree<SmallMessage>;
+    GetMsgHistory : msg_history(id: MessageId) -> Vec<(Option<Time>, String)>;
     GetFirstRootId : first_root_id() -> Option<MessageId>;
     GetLastRootId : last_root_id() -> Option<MessageId>;
     GetPrevRootId : prev_root_id(root_id: MessageId) -> Option<MessageId>;
@@ -237,10 +524,32 @@ euph_room_vault_actions! {
     GetNewestUnseenMsgId : newest_unseen_msg_id() -> Option<MessageId>;
     GetOlderUnseenMsgId : older_unseen_msg_id(id: MessageId) -> Option<MessageId>;
     GetNewerUnseenMsgId : newer_unseen_msg_id(id: MessageId) -> Option<MessageId>;
+    GetOlderMentionMsgId : older_mention_msg_id(id: MessageId, nick: String) -> Option<MessageId>;
+    GetNewerMentionMsgId : newer_mention_msg_id(id: MessageId, nick: String) -> Option<MessageId>;
     GetUnseenMsgsCount : unseen_msgs_count() -> usize;
+    GetRecentNicks : recent_nicks(amount: usize) -> Vec<String>;
     SetSeen : set_seen(id: MessageId, seen: bool) -> ();
     SetOlderSeen : set_older_seen(id: MessageId, seen: bool) -> ();
-    GetChunkAtOffset : chunk_at_offset(amount: usize, offset: usize) -> Vec<Message>;
+    GetChunkAtOffset : chunk_at_offset(filter: ExportFilter, amount: usize, offset: usize) -> Vec<Message>;
+    GetExportRootIds : export_root_ids(filter: ExportFilter) -> Vec<MessageId>;
+    GetExportTreeTimes : export_tree_times(root_ids: Vec<MessageId>) -> Vec<Option<Time>>;
+    SearchFull : search_full(query: Option<String>, nick: Option<String>, since: Option<Time>, until: Option<Time>, limit: Option<usize>) -> Vec<Message>;
+
+    // Export
+    GetExportCheckpoint : export_checkpoint(out: String) -> Option<(MessageId, u64)>;
+    SetExportCheckpoint : set_export_checkpoint(out: String, last_id: MessageId, len: u64) -> ();
+
+    // Draft
+    GetDraft : draft(parent: Option<MessageId>) -> Option<String>;
+    SetDraft : set_draft(parent: Option<MessageId>, content: String) -> ();
+    HasDrafts : has_drafts() -> bool;
+}
+
+euph_room_vault_ignore_actions! {
+    GetMsg : msg(id: MessageId) -> Option<SmallMessage>;
+    GetTree : tree(root_id: MessageId) -> Tree<SmallMessage>;
+    GetExportTree : export_tree(root_id: MessageId, filter: ExportFilter) -> Tree<SmallMessage>;
+    Search : search(query: String, limit: usize, before: Option<MessageId>) -> Vec<SmallMessage>;
 }
 
 impl Action for Join {
@@ -280,7 +589,15 @@ fn insert_msgs(
     room: &str,
     own_user_id: &Option<UserId>,
     msgs: Vec<Message>,
+    overwrite: bool,
 ) -> rusqlite::Result<()> {
+    // Existing messages are only updated if `overwrite` is set. Otherwise,
+    // messages already in the vault are left untouched since they may be more
+    // recent than the ones being inserted.
+    //
+    // Snapshots and logs only contain truncated versions of long messages. An
+    // untruncated version of the same edit that is already in the vault must
+    // not be replaced by them.
     let mut insert_msg = tx.prepare(
         "
         INSERT INTO euph_msgs (
@@ -291,7 +608,7 @@ fn insert_msgs(
         VALUES (
             :room, :id, :parent, :previous_edit_id, :time, :content, :encryption_key_id, :edited, :deleted, :truncated,
             :user_id, :name, :server_id, :server_era, :session_id, :is_staff, :is_manager, :client_address, :real_client_address,
-            (:user_id == :own_user_id OR EXISTS(
+            (:ignored OR :user_id == :own_user_id OR EXISTS(
                 SELECT 1
                 FROM euph_rooms
                 WHERE room = :room
@@ -305,11 +622,15 @@ fn insert_msgs(
             parent = :parent,
             previous_edit_id = :previous_edit_id,
             time = :time,
-            content = :content,
+            content = CASE
+                WHEN :truncated AND NOT truncated AND previous_edit_id IS :previous_edit_id
+                THEN content
+                ELSE :content
+            END,
             encryption_key_id = :encryption_key_id,
             edited = :edited,
             deleted = :deleted,
-            truncated = :truncated,
+            truncated = :truncated AND (truncated OR previous_edit_id IS NOT :previous_edit_id),
 
             user_id = :user_id,
             name = :name,
@@ -320,11 +641,16 @@ fn insert_msgs(
             is_manager = :is_manager,
             client_address = :client_address,
             real_client_address = :real_client_address
+        WHERE :overwrite
         "
     )?;
 
+    // Messages of ignored users are never unseen.
+    let ignores = IgnoreList::new(&get_ignores(tx)?);
+
     let own_user_id = own_user_id.as_ref().map(|u| &u.0);
     for msg in msgs {
+        let ignored = ignores.matches(&msg.sender.id, &msg.sender.name);
         insert_msg.execute(named_params! {
             ":room": room,
             ":id": WSnowflake(msg.id.0),
@@ -346,6 +672,8 @@ fn insert_msgs(
             ":client_address": msg.sender.client_address,
             ":real_client_address": msg.sender.real_client_address,
             ":own_user_id": own_user_id, // May be NULL
+            ":ignored": ignored,
+            ":overwrite": overwrite,
         })?;
     }
 
@@ -437,7 +765,7 @@ impl Action for AddMsg {
         let tx = conn.transaction()?;
 
         let end = self.msg.id;
-        insert_msgs(&tx, &self.room, &self.own_user_id, vec![*self.msg])?;
+        insert_msgs(&tx, &self.room, &self.own_user_id, vec![*self.msg], true)?;
         add_span(&tx, &self.room, self.prev_msg_id, Some(end))?;
 
         tx.commit()?;
@@ -457,7 +785,7 @@ impl Action for AddMsgs {
             let first_msg_id = self.msgs.first().unwrap().id;
             let last_msg_id = self.msgs.last().unwrap().id;
 
-            insert_msgs(&tx, &self.room, &self.own_user_id, self.msgs)?;
+            insert_msgs(&tx, &self.room, &self.own_user_id, self.msgs, true)?;
 
             let end = self.next_msg_id.unwrap_or(last_msg_id);
             add_span(&tx, &self.room, Some(first_msg_id), Some(end))?;
@@ -468,6 +796,142 @@ impl Action for AddMsgs {
     }
 }
 
+impl Action for ImportMsgs {
+    type Result = ();
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        let tx = conn.transaction()?;
+
+        // Messages may be imported into rooms that were never joined.
+        tx.execute(
+            "
+            INSERT OR IGNORE INTO euph_rooms (room, first_joined, last_joined)
+            VALUES (:room, :time, :time)
+            ",
+            named_params! {":room": self.room, ":time": WTime(Time::now())},
+        )?;
+
+        // Without knowing which messages are missing in between, no span can
+        // be added here. Messages already in the vault are kept as they are
+        // since the export may be older than them.
+        insert_msgs(&tx, &self.room, &None, self.msgs, false)?;
+
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+impl Action for AddSpan {
+    type Result = ();
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        let tx = conn.transaction()?;
+        add_span(&tx, &self.room, Some(self.start), Some(self.end))?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+impl Action for UpdateMsg {
+    type Result = ();
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        // Edits of messages we don't know about are ignored since we couldn't
+        // place them in any span.
+        conn.execute(
+            "
+            UPDATE euph_msgs
+            SET
+                previous_edit_id = :previous_edit_id,
+                content = :content,
+                edited = :edited,
+                deleted = :deleted,
+                truncated = :truncated
+            WHERE room = :room
+            AND id = :id
+            ",
+            named_params! {
+                ":room": self.room,
+                ":id": WSnowflake(self.msg.id.0),
+                ":previous_edit_id": self.msg.previous_edit_id.map(WSnowflake),
+                ":content": self.msg.content,
+                ":edited": self.msg.edited.map(WTime),
+                ":deleted": self.msg.deleted.map(WTime),
+                ":truncated": self.msg.truncated,
+            },
+        )?;
+        Ok(())
+    }
+}
+
+impl Action for GetTruncatedMsgIds {
+    type Result = Vec<MessageId>;
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        conn.prepare(
+            "
+            SELECT id
+            FROM euph_msgs
+            WHERE room = ?
+            AND truncated
+            AND deleted IS NULL
+            AND NOT missing
+            ORDER BY id DESC
+            ",
+        )?
+        .query_map([self.room], |row| {
+            row.get::<_, WSnowflake>(0).map(|s| MessageId(s.0))
+        })?
+        .collect::<rusqlite::Result<_>>()
+    }
+}
+
+impl Action for MarkMissing {
+    type Result = ();
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        // All messages with start <= id < end are flagged as missing on the
+        // server unless they are in present. The server may leave messages out
+        // of its logs for other reasons than them being deleted, so their
+        // deletion time is not touched.
+        let tx = conn.transaction()?;
+
+        let ids = tx
+            .prepare(
+                "
+                SELECT id
+                FROM euph_msgs
+                WHERE room = ?
+                AND id >= ?
+                AND id < ?
+                ",
+            )?
+            .query_map(
+                params![self.room, WSnowflake(self.start.0), WSnowflake(self.end.0)],
+                |row| row.get::<_, WSnowflake>(0).map(|s| MessageId(s.0)),
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let present = self.present.into_iter().collect::<HashSet<_>>();
+        let mut stmt = tx.prepare(
+            "
+            UPDATE euph_msgs
+            SET missing = ?
+            WHERE room = ?
+            AND id = ?
+            ",
+        )?;
+        for id in ids {
+            let missing = !present.contains(&id);
+            stmt.execute(params![missing, self.room, WSnowflake(id.0)])?;
+        }
+        drop(stmt);
+
+        tx.commit()?;
+        Ok(())
+    }
+}
+
 impl Action for GetLastSpan {
     type Result = Option<(Option<MessageId>, Option<MessageId>)>;
 
@@ -493,6 +957,40 @@ impl Action for GetLastSpan {
     }
 }
 
+impl Action for GetGaps {
+    type Result = Vec<(MessageId, MessageId)>;
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        // Spans never overlap since add_span combines them, so every span
+        // except the first one is preceded by a gap. Each gap is bounded by the
+        // end of the previous span and the start of the next span, both of
+        // which are known messages.
+        let spans = conn
+            .prepare(
+                "
+                SELECT start, end
+                FROM euph_spans
+                WHERE room = ?
+                ORDER BY start ASC
+                ",
+            )?
+            .query_map([self.room], |row| {
+                Ok((
+                    row.get::<_, Option<WSnowflake>>(0)?.map(|s| MessageId(s.0)),
+                    row.get::<_, Option<WSnowflake>>(1)?.map(|s| MessageId(s.0)),
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let gaps = spans
+            .iter()
+            .zip(spans.iter().skip(1))
+            .filter_map(|((_, end), (start, _))| Some(((*end)?, (*start)?)))
+            .collect();
+        Ok(gaps)
+    }
+}
+
 impl Action for GetPath {
     type Result = Path<MessageId>;
 
@@ -529,20 +1027,24 @@ impl Action for GetMsg {
         let msg = conn
             .query_row(
                 "
-                SELECT id, parent, time, name, content, seen
+                SELECT id, parent, time, name, content, seen, deleted IS NOT NULL OR missing, user_id
                 FROM euph_msgs
                 WHERE room = ?
                 AND id = ?
                 ",
                 params![self.room, WSnowflake(self.id.0)],
                 |row| {
+                    let nick: String = row.get(3)?;
+                    let ignored = self.ignores.matches(&UserId(row.get(7)?), &nick);
                     Ok(SmallMessage {
                         id: MessageId(row.get::<_, WSnowflake>(0)?.0),
                         parent: row.get::<_, Option<WSnowflake>>(1)?.map(|s| MessageId(s.0)),
                         time: row.get::<_, WTime>(2)?.0,
-                        nick: row.get(3)?,
+                        nick,
                         content: row.get(4)?,
                         seen: row.get(5)?,
+                        deleted: row.get(6)?,
+                        ignored,
                     })
                 },
             )
@@ -596,6 +1098,27 @@ impl Action for GetFullMsg {
     }
 }
 
+impl Action for GetMsgHistory {
+    type Result = Vec<(Option<Time>, String)>;
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        // The original version has no edit time and thus comes first.
+        conn.prepare(
+            "
+            SELECT edited, content
+            FROM euph_msg_edits
+            WHERE room = ?
+            AND id = ?
+            ORDER BY edited ASC, rowid ASC
+            ",
+        )?
+        .query_map(params![self.room, WSnowflake(self.id.0)], |row| {
+            Ok((row.get::<_, Option<WTime>>(0)?.map(|t| t.0), row.get(1)?))
+        })?
+        .collect::<rusqlite::Result<_>>()
+    }
+}
+
 impl Action for GetTree {
     type Result = Tree<SmallMessage>;
 
@@ -613,20 +1136,24 @@ impl Action for GetTree {
                         ON tree.room = euph_msgs.room
                         AND tree.id = euph_msgs.parent
                 )
-                SELECT id, parent, time, name, content, seen
+                SELECT id, parent, time, name, content, seen, deleted IS NOT NULL OR missing, user_id
                 FROM euph_msgs
                 JOIN tree USING (room, id)
                 ORDER BY id ASC
                 ",
             )?
             .query_map(params![self.room, WSnowflake(self.root_id.0)], |row| {
+                let nick: String = row.get(3)?;
+                let ignored = self.ignores.matches(&UserId(row.get(7)?), &nick);
                 Ok(SmallMessage {
                     id: MessageId(row.get::<_, WSnowflake>(0)?.0),
                     parent: row.get::<_, Option<WSnowflake>>(1)?.map(|s| MessageId(s.0)),
                     time: row.get::<_, WTime>(2)?.0,
-                    nick: row.get(3)?,
+                    nick,
                     content: row.get(4)?,
                     seen: row.get(5)?,
+                    deleted: row.get(6)?,
+                    ignored,
                 })
             })?
             .collect::<rusqlite::Result<_>>()?;
@@ -907,6 +1434,69 @@ impl Action for GetNewerUnseenMsgId {
     }
 }
 
+/// Find the first message in the result of a query of `(id, content)` rows
+/// that mentions a nick.
+///
+/// Mentions can't be detected in SQL, so the query should only return rows
+/// containing an `@` and the rest is done here.
+fn first_mention(
+    conn: &Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+    nick: &str,
+) -> rusqlite::Result<Option<MessageId>> {
+    let mut query = conn.prepare(sql)?;
+    let mut rows = query.query(params)?;
+    while let Some(row) = rows.next()? {
+        let content = row.get::<_, String>(1)?;
+        if euph::mentions_nick(&content, nick) {
+            let id = row.get::<_, WSnowflake>(0)?;
+            return Ok(Some(MessageId(id.0)));
+        }
+    }
+    Ok(None)
+}
+
+impl Action for GetOlderMentionMsgId {
+    type Result = Option<MessageId>;
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        first_mention(
+            conn,
+            "
+            SELECT id, content
+            FROM euph_msgs
+            WHERE room = ?
+            AND id < ?
+            AND INSTR(content, '@') > 0
+            ORDER BY id DESC
+            ",
+            params![self.room, WSnowflake(self.id.0)],
+            &self.nick,
+        )
+    }
+}
+
+impl Action for GetNewerMentionMsgId {
+    type Result = Option<MessageId>;
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        first_mention(
+            conn,
+            "
+            SELECT id, content
+            FROM euph_msgs
+            WHERE room = ?
+            AND id > ?
+            AND INSTR(content, '@') > 0
+            ORDER BY id ASC
+            ",
+            params![self.room, WSnowflake(self.id.0)],
+            &self.nick,
+        )
+    }
+}
+
 impl Action for GetUnseenMsgsCount {
     type Result = usize;
 
@@ -926,6 +1516,27 @@ impl Action for GetUnseenMsgsCount {
     }
 }
 
+impl Action for GetRecentNicks {
+    type Result = Vec<String>;
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        conn.prepare(
+            "
+            SELECT name
+            FROM euph_msgs
+            WHERE room = ?
+            AND name IS NOT NULL
+            AND name != ''
+            GROUP BY name
+            ORDER BY MAX(id) DESC
+            LIMIT ?
+            ",
+        )?
+        .query_map(params![self.room, self.amount], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()
+    }
+}
+
 impl Action for SetSeen {
     type Result = ();
 
@@ -967,19 +1578,49 @@ impl Action for GetChunkAtOffset {
     fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
         let mut query = conn.prepare(
             "
+            WITH RECURSIVE
+            thread (room, id) AS (
+                SELECT :room, :thread
+                WHERE :thread IS NOT NULL
+            UNION
+                SELECT euph_msgs.room, euph_msgs.id
+                FROM euph_msgs
+                JOIN thread
+                    ON thread.room = euph_msgs.room
+                    AND thread.id = euph_msgs.parent
+            )
             SELECT
                 id, parent, previous_edit_id, time, content, encryption_key_id, edited, deleted, truncated,
                 user_id, name, server_id, server_era, session_id, is_staff, is_manager, client_address, real_client_address
             FROM euph_msgs
-            WHERE room = ?
+            WHERE room = :room
+            AND (:thread IS NULL OR id IN (SELECT id FROM thread))
+            AND (:since IS NULL OR time >= :since)
+            AND (:until IS NULL OR time <= :until)
+            AND (:nick IS NULL OR name = :nick COLLATE NOCASE)
+            AND (NOT :exclude_deleted OR (deleted IS NULL AND NOT missing))
+            AND (:after IS NULL OR id > :after)
+            AND (:up_to IS NULL OR id <= :up_to)
             ORDER BY id ASC
-            LIMIT ?
-            OFFSET ?
+            LIMIT :amount
+            OFFSET :offset
             ",
         )?;
 
+        let params = named_params! {
+            ":room": self.room,
+            ":thread": self.filter.thread.map(|id| WSnowflake(id.0)),
+            ":since": self.filter.since.map(WTime),
+            ":until": self.filter.until.map(WTime),
+            ":nick": self.filter.nick,
+            ":exclude_deleted": self.filter.exclude_deleted,
+            ":after": self.filter.after.map(|id| WSnowflake(id.0)),
+            ":up_to": self.filter.up_to.map(|id| WSnowflake(id.0)),
+            ":amount": self.amount,
+            ":offset": self.offset,
+        };
         let messages = query
-            .query_map(params![self.room, self.amount, self.offset], |row| {
+            .query_map(params, |row| {
                 Ok(Message {
                     id: MessageId(row.get::<_, WSnowflake>(0)?.0),
                     parent: row.get::<_, Option<WSnowflake>>(1)?.map(|s| MessageId(s.0)),
@@ -1008,6 +1649,444 @@ impl Action for GetChunkAtOffset {
     }
 }
 
+impl Action for GetExportRootIds {
+    type Result = Vec<MessageId>;
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        if let Some(thread) = self.filter.thread {
+            return Ok(vec![thread]);
+        }
+
+        if !self.filter.filters_msgs() {
+            return conn
+                .prepare(
+                    "
+                    SELECT id
+                    FROM euph_trees
+                    WHERE room = ?
+                    ORDER BY id ASC
+                    ",
+                )?
+                .query_map([self.room], |row| {
+                    row.get::<_, WSnowflake>(0).map(|s| MessageId(s.0))
+                })?
+                .collect::<rusqlite::Result<_>>();
+        }
+
+        // The roots of all trees containing matching messages, found by
+        // walking up from the matching messages.
+        conn.prepare(
+            "
+            WITH RECURSIVE
+            ancestors (room, id, parent) AS (
+                SELECT room, id, parent
+                FROM euph_msgs
+                WHERE room = :room
+                AND (:since IS NULL OR time >= :since)
+                AND (:until IS NULL OR time <= :until)
+                AND (:nick IS NULL OR name = :nick COLLATE NOCASE)
+                AND (NOT :exclude_deleted OR (deleted IS NULL AND NOT missing))
+                AND (:after IS NULL OR id > :after)
+                AND (:up_to IS NULL OR id <= :up_to)
+            UNION
+                SELECT euph_msgs.room, euph_msgs.id, euph_msgs.parent
+                FROM euph_msgs
+                JOIN ancestors
+                    ON ancestors.room = euph_msgs.room
+                    AND ancestors.parent = euph_msgs.id
+            )
+            SELECT id
+            FROM ancestors
+            WHERE parent IS NULL
+            UNION
+            SELECT parent
+            FROM ancestors
+            WHERE parent IS NOT NULL
+            AND NOT EXISTS(
+                SELECT *
+                FROM euph_msgs
+                WHERE room = ancestors.room
+                AND id = ancestors.parent
+            )
+            ORDER BY id ASC
+            ",
+        )?
+        .query_map(
+            named_params! {
+                ":room": self.room,
+                ":since": self.filter.since.map(WTime),
+                ":until": self.filter.until.map(WTime),
+                ":nick": self.filter.nick,
+                ":exclude_deleted": self.filter.exclude_deleted,
+                ":after": self.filter.after.map(|id| WSnowflake(id.0)),
+                ":up_to": self.filter.up_to.map(|id| WSnowflake(id.0)),
+            },
+            |row| row.get::<_, WSnowflake>(0).map(|s| MessageId(s.0)),
+        )?
+        .collect::<rusqlite::Result<_>>()
+    }
+}
+
+impl Action for GetExportTreeTimes {
+    type Result = Vec<Option<Time>>;
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        // If the root of a tree is not in the vault, the time of its oldest
+        // reply is used instead.
+        let mut query = conn.prepare(
+            "
+            SELECT COALESCE(
+                (
+                    SELECT time
+                    FROM euph_msgs
+                    WHERE room = :room
+                    AND id = :id
+                ),
+                (
+                    SELECT MIN(time)
+                    FROM euph_msgs
+                    WHERE room = :room
+                    AND parent = :id
+                )
+            )
+            ",
+        )?;
+
+        self.root_ids
+            .into_iter()
+            .map(|id| {
+                query.query_row(
+                    named_params! { ":room": self.room, ":id": WSnowflake(id.0) },
+                    |row| row.get::<_, Option<WTime>>(0).map(|t| t.map(|t| t.0)),
+                )
+            })
+            .collect()
+    }
+}
+
+impl Action for GetExportTree {
+    type Result = Tree<SmallMessage>;
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        // Messages that don't match the filter are still included if one of
+        // their replies matches, otherwise the replies would be cut off from
+        // the rest of the tree.
+        let msgs = conn
+            .prepare(
+                "
+                WITH RECURSIVE
+                tree (room, id) AS (
+                    VALUES (:room, :root)
+                UNION
+                    SELECT euph_msgs.room, euph_msgs.id
+                    FROM euph_msgs
+                    JOIN tree
+                        ON tree.room = euph_msgs.room
+                        AND tree.id = euph_msgs.parent
+                ),
+                context (room, id) AS (
+                    SELECT room, id
+                    FROM euph_msgs
+                    JOIN tree USING (room, id)
+                    WHERE (:since IS NULL OR time >= :since)
+                    AND (:until IS NULL OR time <= :until)
+                    AND (:nick IS NULL OR name = :nick COLLATE NOCASE)
+                    AND (NOT :exclude_deleted OR (deleted IS NULL AND NOT missing))
+                    AND (:after IS NULL OR id > :after)
+                    AND (:up_to IS NULL OR id <= :up_to)
+                UNION
+                    SELECT euph_msgs.room, euph_msgs.parent
+                    FROM euph_msgs
+                    JOIN context USING (room, id)
+                    WHERE euph_msgs.parent IS NOT NULL
+                    AND euph_msgs.id != :root
+                )
+                SELECT id, parent, time, name, content, seen, deleted IS NOT NULL OR missing, user_id
+                FROM euph_msgs
+                JOIN context USING (room, id)
+                ORDER BY id ASC
+                ",
+            )?
+            .query_map(
+                named_params! {
+                    ":room": self.room,
+                    ":root": WSnowflake(self.root_id.0),
+                    ":since": self.filter.since.map(WTime),
+                    ":until": self.filter.until.map(WTime),
+                    ":nick": self.filter.nick,
+                    ":exclude_deleted": self.filter.exclude_deleted,
+                    ":after": self.filter.after.map(|id| WSnowflake(id.0)),
+                    ":up_to": self.filter.up_to.map(|id| WSnowflake(id.0)),
+                },
+                |row| {
+                    let nick: String = row.get(3)?;
+                    let ignored = self.ignores.matches(&UserId(row.get(7)?), &nick);
+                    Ok(SmallMessage {
+                        id: MessageId(row.get::<_, WSnowflake>(0)?.0),
+                        parent: row.get::<_, Option<WSnowflake>>(1)?.map(|s| MessageId(s.0)),
+                        time: row.get::<_, WTime>(2)?.0,
+                        nick,
+                        content: row.get(4)?,
+                        seen: row.get(5)?,
+                        deleted: row.get(6)?,
+                        ignored,
+                    })
+                },
+            )?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(Tree::new(self.root_id, msgs))
+    }
+}
+
+/// Turn a user-provided search string into an fts5 query.
+///
+/// Every whitespace-separated word is quoted so the fts5 query syntax can't
+/// lead to errors. A message must contain all words, but the words may appear
+/// in any order. The last word is treated as a prefix.
+fn fts_query(query: &str) -> String {
+    let mut words = query
+        .split_whitespace()
+        .map(|w| format!("\"{}\"", w.replace('"', "\"\"")))
+        .collect::<Vec<_>>();
+    if let Some(last) = words.last_mut() {
+        last.push('*');
+    }
+    words.join(" ")
+}
+
+impl Action for Search {
+    type Result = Vec<SmallMessage>;
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        let query = fts_query(&self.query);
+        if query.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let msgs = conn
+            .prepare(
+                "
+                SELECT euph_msgs.id, parent, time, name, euph_msgs.content, seen, deleted IS NOT NULL OR missing, user_id
+                FROM euph_msgs_fts
+                JOIN euph_msgs
+                    ON euph_msgs.rowid = euph_msgs_fts.rowid
+                WHERE euph_msgs_fts MATCH :query
+                AND euph_msgs.room = :room
+                AND (:before IS NULL OR euph_msgs.id < :before)
+                ORDER BY euph_msgs.id DESC
+                LIMIT :limit
+                ",
+            )?
+            .query_map(
+                named_params! {
+                    ":query": query,
+                    ":room": self.room,
+                    ":before": self.before.map(|id| WSnowflake(id.0)),
+                    ":limit": self.limit,
+                },
+                |row| {
+                    let nick: String = row.get(3)?;
+                    let ignored = self.ignores.matches(&UserId(row.get(7)?), &nick);
+                    Ok(SmallMessage {
+                        id: MessageId(row.get::<_, WSnowflake>(0)?.0),
+                        parent: row.get::<_, Option<WSnowflake>>(1)?.map(|s| MessageId(s.0)),
+                        time: row.get::<_, WTime>(2)?.0,
+                        nick,
+                        content: row.get(4)?,
+                        seen: row.get(5)?,
+                        deleted: row.get(6)?,
+                        ignored,
+                    })
+                },
+            )?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(msgs)
+    }
+}
+
+impl Action for SearchFull {
+    type Result = Vec<Message>;
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        let query = self.query.map(|q| fts_query(&q)).filter(|q| !q.is_empty());
+
+        let mut query_stmt = conn.prepare(
+            "
+            SELECT
+                id, parent, previous_edit_id, time, content, encryption_key_id, edited, deleted, truncated,
+                user_id, name, server_id, server_era, session_id, is_staff, is_manager, client_address, real_client_address
+            FROM euph_msgs
+            WHERE room = :room
+            AND (:query IS NULL OR rowid IN (
+                SELECT rowid
+                FROM euph_msgs_fts
+                WHERE euph_msgs_fts MATCH :query
+            ))
+            AND (:nick IS NULL OR name = :nick COLLATE NOCASE)
+            AND (:since IS NULL OR time >= :since)
+            AND (:until IS NULL OR time <= :until)
+            ORDER BY id DESC
+            LIMIT coalesce(:limit, -1)
+            "
+        )?;
+
+        let mut messages = query_stmt
+            .query_map(
+                named_params! {
+                    ":room": self.room,
+                    ":query": query,
+                    ":nick": self.nick,
+                    ":since": self.since.map(WTime),
+                    ":until": self.until.map(WTime),
+                    ":limit": self.limit,
+                },
+                |row| {
+                    Ok(Message {
+                        id: MessageId(row.get::<_, WSnowflake>(0)?.0),
+                        parent: row.get::<_, Option<WSnowflake>>(1)?.map(|s| MessageId(s.0)),
+                        previous_edit_id: row.get::<_, Option<WSnowflake>>(2)?.map(|s| s.0),
+                        time: row.get::<_, WTime>(3)?.0,
+                        content: row.get(4)?,
+                        encryption_key_id: row.get(5)?,
+                        edited: row.get::<_, Option<WTime>>(6)?.map(|t| t.0),
+                        deleted: row.get::<_, Option<WTime>>(7)?.map(|t| t.0),
+                        truncated: row.get(8)?,
+                        sender: SessionView {
+                            id: UserId(row.get(9)?),
+                            name: row.get(10)?,
+                            server_id: row.get(11)?,
+                            server_era: row.get(12)?,
+                            session_id: SessionId(row.get(13)?),
+                            is_staff: row.get(14)?,
+                            is_manager: row.get(15)?,
+                            client_address: row.get(16)?,
+                            real_client_address: row.get(17)?,
+                        },
+                    })
+                },
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        // Newest messages were selected first so the limit applies to them,
+        // but results should be in chronological order.
+        messages.reverse();
+        Ok(messages)
+    }
+}
+
+impl Action for GetExportCheckpoint {
+    type Result = Option<(MessageId, u64)>;
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        conn.query_row(
+            "
+            SELECT last_id, len
+            FROM euph_export_checkpoints
+            WHERE room = ?
+            AND out = ?
+            ",
+            [self.room, self.out],
+            |row| Ok((MessageId(row.get::<_, WSnowflake>(0)?.0), row.get(1)?)),
+        )
+        .optional()
+    }
+}
+
+impl Action for SetExportCheckpoint {
+    type Result = ();
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        conn.execute(
+            "
+            INSERT INTO euph_export_checkpoints (room, out, last_id, len)
+            VALUES (:room, :out, :last_id, :len)
+            ON CONFLICT (room, out) DO UPDATE
+            SET last_id = :last_id, len = :len
+            ",
+            named_params! {
+                ":room": self.room,
+                ":out": self.out,
+                ":last_id": WSnowflake(self.last_id.0),
+                ":len": self.len,
+            },
+        )?;
+        Ok(())
+    }
+}
+
+impl Action for GetDraft {
+    type Result = Option<String>;
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        conn.prepare(
+            "
+            SELECT content
+            FROM euph_drafts
+            WHERE room = ?
+            AND parent IS ?
+            ",
+        )?
+        .query_row(
+            params![self.room, self.parent.map(|p| WSnowflake(p.0))],
+            |row| row.get(0),
+        )
+        .optional()
+    }
+}
+
+impl Action for SetDraft {
+    type Result = ();
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        let tx = conn.transaction()?;
+
+        // Parents may be NULL, so the unique constraint can't be used to
+        // replace existing drafts.
+        tx.execute(
+            "
+            DELETE FROM euph_drafts
+            WHERE room = ?
+            AND parent IS ?
+            ",
+            params![self.room, self.parent.map(|p| WSnowflake(p.0))],
+        )?;
+
+        if !self.content.is_empty() {
+            tx.execute(
+                "
+                INSERT INTO euph_drafts (room, parent, content)
+                VALUES (?, ?, ?)
+                ",
+                params![
+                    self.room,
+                    self.parent.map(|p| WSnowflake(p.0)),
+                    self.content
+                ],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+impl Action for HasDrafts {
+    type Result = bool;
+
+    fn run(self, conn: &mut Connection) -> rusqlite::Result<Self::Result> {
+        conn.prepare(
+            "
+            SELECT EXISTS (
+                SELECT *
+                FROM euph_drafts
+                WHERE room = ?
+            )
+            ",
+        )?
+        .query_row(params![self.room], |row| row.get(0))
+    }
+}
+
 #[async_trait]
 impl MsgStore<SmallMessage> for EuphRoomVault {
     type Error = vault::tokio::Error;
@@ -1072,10 +2151,42 @@ impl MsgStore<SmallMessage> for EuphRoomVault {
         self.newer_unseen_msg_id(*id).await
     }
 
+    async fn older_mention_msg_id(
+        &self,
+        id: &MessageId,
+        nick: &str,
+    ) -> Result<Option<MessageId>, Self::Error> {
+        self.older_mention_msg_id(*id, nick.to_string()).await
+    }
+
+    async fn newer_mention_msg_id(
+        &self,
+        id: &MessageId,
+        nick: &str,
+    ) -> Result<Option<MessageId>, Self::Error> {
+        self.newer_mention_msg_id(*id, nick.to_string()).await
+    }
+
+    async fn gaps(&self) -> Result<Vec<(MessageId, MessageId)>, Self::Error> {
+        self.gaps().await
+    }
+
     async fn unseen_msgs_count(&self) -> Result<usize, Self::Error> {
         self.unseen_msgs_count().await
     }
 
+    async fn draft(&self, parent: Option<&MessageId>) -> Result<Option<String>, Self::Error> {
+        self.draft(parent.copied()).await
+    }
+
+    async fn set_draft(
+        &self,
+        parent: Option<&MessageId>,
+        content: &str,
+    ) -> Result<(), Self::Error> {
+        self.set_draft(parent.copied(), content.to_string()).await
+    }
+
     async fn set_seen(&self, id: &MessageId, seen: bool) -> Result<(), Self::Error> {
         self.set_seen(*id, seen).await
     }
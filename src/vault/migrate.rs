@@ -1,7 +1,7 @@
 use rusqlite::Transaction;
 use vault::Migration;
 
-pub const MIGRATIONS: [Migration; 2] = [m1, m2];
+pub const MIGRATIONS: [Migration; 9] = [m1, m2, m3, m4, m5, m6, m7, m8, m9];
 
 fn m1(tx: &mut Transaction<'_>, nr: usize, total: usize) -> rusqlite::Result<()> {
     eprintln!("Migrating vault from {} to {} (out of {total})", nr, nr + 1);
@@ -78,3 +78,110 @@ fn m2(tx: &mut Transaction<'_>, nr: usize, total: usize) -> rusqlite::Result<()>
         ",
     )
 }
+
+fn m3(tx: &mut Transaction<'_>, nr: usize, total: usize) -> rusqlite::Result<()> {
+    eprintln!("Migrating vault from {} to {} (out of {total})", nr, nr + 1);
+    tx.execute_batch(
+        "
+        CREATE VIRTUAL TABLE euph_msgs_fts
+        USING fts5 (
+            content,
+            content = 'euph_msgs'
+        );
+
+        INSERT INTO euph_msgs_fts (euph_msgs_fts)
+        VALUES ('rebuild');
+        ",
+    )
+}
+
+fn m4(tx: &mut Transaction<'_>, nr: usize, total: usize) -> rusqlite::Result<()> {
+    eprintln!("Migrating vault from {} to {} (out of {total})", nr, nr + 1);
+    tx.execute_batch(
+        "
+        CREATE INDEX euph_idx_msgs_room_id_truncated
+        ON euph_msgs (room, id)
+        WHERE truncated;
+        ",
+    )
+}
+
+fn m5(tx: &mut Transaction<'_>, nr: usize, total: usize) -> rusqlite::Result<()> {
+    eprintln!("Migrating vault from {} to {} (out of {total})", nr, nr + 1);
+    tx.execute_batch(
+        "
+        CREATE TABLE euph_msg_edits (
+            room    TEXT NOT NULL,
+            id      INT  NOT NULL,
+            content TEXT NOT NULL,
+            edited  INT,
+
+            FOREIGN KEY (room, id) REFERENCES euph_msgs (room, id)
+                ON DELETE CASCADE
+        ) STRICT;
+
+        CREATE INDEX euph_idx_msg_edits_room_id
+        ON euph_msg_edits (room, id);
+        ",
+    )
+}
+
+fn m6(tx: &mut Transaction<'_>, nr: usize, total: usize) -> rusqlite::Result<()> {
+    eprintln!("Migrating vault from {} to {} (out of {total})", nr, nr + 1);
+    tx.execute_batch(
+        "
+        CREATE TABLE euph_drafts (
+            room    TEXT NOT NULL,
+            parent  INT,
+            content TEXT NOT NULL,
+
+            UNIQUE (room, parent),
+            FOREIGN KEY (room) REFERENCES euph_rooms (room)
+                ON DELETE CASCADE
+        ) STRICT;
+        ",
+    )
+}
+
+fn m7(tx: &mut Transaction<'_>, nr: usize, total: usize) -> rusqlite::Result<()> {
+    eprintln!("Migrating vault from {} to {} (out of {total})", nr, nr + 1);
+    tx.execute_batch(
+        "
+        CREATE TABLE euph_ignores (
+            kind  TEXT NOT NULL,
+            value TEXT NOT NULL,
+
+            PRIMARY KEY (kind, value),
+            CHECK (kind IN ('user_id', 'nick'))
+        ) STRICT;
+        ",
+    )
+}
+
+fn m8(tx: &mut Transaction<'_>, nr: usize, total: usize) -> rusqlite::Result<()> {
+    eprintln!("Migrating vault from {} to {} (out of {total})", nr, nr + 1);
+    tx.execute_batch(
+        "
+        CREATE TABLE euph_export_checkpoints (
+            room    TEXT NOT NULL,
+            out     TEXT NOT NULL,
+            last_id INT  NOT NULL,
+            len     INT  NOT NULL,
+
+            PRIMARY KEY (room, out),
+            FOREIGN KEY (room) REFERENCES euph_rooms (room)
+                ON DELETE CASCADE
+        ) STRICT;
+        ",
+    )
+}
+
+fn m9(tx: &mut Transaction<'_>, nr: usize, total: usize) -> rusqlite::Result<()> {
+    eprintln!("Migrating vault from {} to {} (out of {total})", nr, nr + 1);
+    tx.execute_batch(
+        "
+        ALTER TABLE euph_msgs
+        ADD COLUMN missing INTEGER NOT NULL DEFAULT FALSE;
+        ",
+    )
+}
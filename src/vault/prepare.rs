@@ -118,5 +118,56 @@ pub fn prepare(conn: &mut Connection) -> rusqlite::Result<()> {
         ",
     )?;
 
+    // Keep the full text search index in sync with the messages. Since the
+    // index doesn't store the content itself, removing a message from it
+    // requires the content that was indexed.
+    conn.execute_batch(
+        "
+        CREATE TEMPORARY TRIGGER ef_delete_msg
+        AFTER DELETE ON main.euph_msgs
+        BEGIN
+            INSERT INTO euph_msgs_fts (euph_msgs_fts, rowid, content)
+            VALUES ('delete', old.rowid, old.content);
+        END;
+
+        CREATE TEMPORARY TRIGGER ef_insert_msg
+        AFTER INSERT ON main.euph_msgs
+        BEGIN
+            INSERT INTO euph_msgs_fts (rowid, content)
+            VALUES (new.rowid, new.content);
+        END;
+
+        CREATE TEMPORARY TRIGGER ef_update_msg
+        AFTER UPDATE OF content ON main.euph_msgs
+        WHEN old.content != new.content
+        BEGIN
+            INSERT INTO euph_msgs_fts (euph_msgs_fts, rowid, content)
+            VALUES ('delete', old.rowid, old.content);
+
+            INSERT INTO euph_msgs_fts (rowid, content)
+            VALUES (new.rowid, new.content);
+        END;
+        ",
+    )?;
+
+    // Remember the previous versions of edited messages. Messages whose
+    // content changes because they were untruncated or replaced by a truncated
+    // copy were not edited, and neither were messages whose edit time stayed
+    // the same.
+    conn.execute_batch(
+        "
+        CREATE TEMPORARY TRIGGER eme_update_msg
+        AFTER UPDATE OF content ON main.euph_msgs
+        WHEN old.content != new.content
+        AND old.edited IS NOT new.edited
+        AND NOT old.truncated
+        AND NOT new.truncated
+        BEGIN
+            INSERT INTO euph_msg_edits (room, id, content, edited)
+            VALUES (old.room, old.id, old.content, old.edited);
+        END;
+        ",
+    )?;
+
     Ok(())
 }